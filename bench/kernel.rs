@@ -0,0 +1,46 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/19 10:05
+ * @Email: uyplayer@qq.com
+ * @File: kernel
+ * @Software: RustRover
+ * @Dir: tech_analysis / bench
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+//! compares the single pass kernel regression with the tradingview loops, run with `cargo bench --bench kernel`
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use polars::prelude::*;
+use tech_analysis::{gaussian, gaussian_tv, rational_quadratic, rational_quadratic_tv};
+
+
+/// The best time of `runs` calls.
+fn best_of<T, F: FnMut() -> T>(runs: usize, mut f: F) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    for bars in [10_000, 100_000] {
+        let src = Series::new("close", (0..bars).map(|i| 100.0 + (i as f64 / 50.0).sin() * 10.0).collect::<Vec<f64>>());
+
+        let fast = best_of(5, || rational_quadratic(&src, 8, 8.0, 25).unwrap());
+        let tv = best_of(5, || rational_quadratic_tv(&src, 8, 8.0, 25).unwrap());
+        println!("rational_quadratic    {:>7} bars: {:>12?}, rational_quadratic_tv: {:>12?}, speedup {:.1}x",
+                 bars, fast, tv, tv.as_secs_f64() / fast.as_secs_f64());
+
+        let fast = best_of(5, || gaussian(&src, 6, 25).unwrap());
+        let tv = best_of(5, || gaussian_tv(&src, 6, 25).unwrap());
+        println!("gaussian              {:>7} bars: {:>12?}, gaussian_tv:           {:>12?}, speedup {:.1}x",
+                 bars, fast, tv, tv.as_secs_f64() / fast.as_secs_f64());
+    }
+}
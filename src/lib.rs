@@ -19,6 +19,6 @@
 
 
 mod lorentzian_classification;
-pub use lorentzian_classification::{rational_quadratic,rational_quadratic_tv,gaussian,gaussian_tv,normalizer,rescale,rma_indicator,Settings,Filters,KernelFilter,Direction};
+pub use lorentzian_classification::{rational_quadratic,rational_quadratic_tv,gaussian,gaussian_tv,periodic,periodic_tv,locally_periodic,locally_periodic_tv,epanechnikov,epanechnikov_tv,laplace,laplace_tv,rational_quadratic_local,gaussian_local,rational_quadratic_time,gaussian_time,Kernel,TimeKernel,kernel_regression,kernel_regression_with_policy,NanPolicy,Lookahead,kernel_smoothing,LocalPolynomial,local_polynomial_regression,time_kernel_regression,RationalQuadraticKernel,GaussianKernel,PeriodicKernel,LocallyPeriodicKernel,EpanechnikovKernel,LaplaceKernel,KernelStream,RationalQuadraticStream,GaussianStream,Envelope,nadaraya_watson_envelope,TaExpr,TaNameSpace,CrossValidation,BandwidthSelection,select_bandwidth,TurningPointKind,TurningPoint,kernel_dynamics,turning_points,VolatilityMeasure,AdaptiveBandwidth,adaptive_kernel_regression,rational_quadratic_adaptive,gaussian_adaptive,kernel_confidence,normalizer,rescale,rma_indicator,ConfigCheck,Settings,Filters,KernelFilter,Direction,FeatureSpec,DEFAULT_FEATURES,lorentzian_classification,lorentzian_classification_with_labeler,explain_classification,Explanation,feature_series,feature_frame,LorentzianAnn,Neighbors,lorentzian_distance,Labeler,LabelOrientation,FixedHorizonLabeler,TripleBarrierLabeler,TrendScanningLabeler,volatility_filter,regime_filter,adx_filter,ema_filter,sma_filter,ExitMode,ExitReason,TradeEventKind,TradeEvent,TradeBar,PositionStateMachine,trade_events,kernel_signals,TradeStats};
 
 
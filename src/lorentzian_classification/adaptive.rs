@@ -0,0 +1,337 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/26 10:05
+ * @Email: uyplayer@qq.com
+ * @File: adaptive
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! kernel regression with a bandwidth adapting to the volatility bar by bar
+
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::regression::{kernel_values, GaussianKernel, Kernel, NanPolicy, RationalQuadraticKernel};
+use crate::lorentzian_classification::types::ConfigCheck;
+
+
+/// The volatility measure of an [`AdaptiveBandwidth`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum VolatilityMeasure {
+    /// Average true range of the high, low and close columns.
+    #[default]
+    Atr,
+    /// Standard deviation of the source.
+    Stdev,
+}
+
+/// A kernel bandwidth adapting to the volatility.
+///
+/// The volatility ratio of a bar is the fast volatility divided by the slow volatility, both from the
+/// current and past bars only. The bandwidth of the bar is `look_back / ratio` clamped to
+/// `min_look_back..=max_look_back`, so fast markets are smoothed less and quiet markets more.
+/// The bars before the slow volatility is known have no bandwidth.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveBandwidth {
+    /// The volatility measure.
+    pub measure: VolatilityMeasure,
+    /// The length of the fast volatility.
+    pub fast_length: i32,
+    /// The length of the slow volatility.
+    pub slow_length: i32,
+    /// The smallest bandwidth.
+    pub min_look_back: f64,
+    /// The biggest bandwidth.
+    pub max_look_back: f64,
+}
+
+impl Default for AdaptiveBandwidth {
+    fn default() -> Self {
+        AdaptiveBandwidth {
+            measure: VolatilityMeasure::Atr,
+            fast_length: 10,
+            slow_length: 50,
+            min_look_back: 2.0,
+            max_look_back: 32.0,
+        }
+    }
+}
+
+impl ConfigCheck for AdaptiveBandwidth {
+    type Output = ();
+    fn configuration_check(&self) -> Self::Output {
+        if self.fast_length <= 0 || self.slow_length <= 0 {
+            panic!("volatility lengths must be bigger than zero");
+        }
+        if !(self.min_look_back > 0.0 && self.min_look_back <= self.max_look_back) {
+            panic!("min_look_back must be bigger than zero and not bigger than max_look_back");
+        }
+    }
+}
+
+impl AdaptiveBandwidth {
+    /// The volatility ratio of every bar, `ohlc` holds the high, low and close values the ATR needs.
+    pub(crate) fn volatility_ratio(&self, src: &[f64], ohlc: Option<(&[f64], &[f64], &[f64])>) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+        let (fast, slow) = match self.measure {
+            VolatilityMeasure::Atr => {
+                let (high, low, close) = ohlc.ok_or("the ATR bandwidth needs the high, low and close columns")?;
+                (helper::atr(high, low, close, self.fast_length as usize), helper::atr(high, low, close, self.slow_length as usize))
+            }
+            VolatilityMeasure::Stdev => (helper::stdev(src, self.fast_length as usize), helper::stdev(src, self.slow_length as usize)),
+        };
+        Ok(fast
+            .iter()
+            .zip(&slow)
+            .map(|(&fast, &slow)| match (fast, slow) {
+                (fast, slow) if fast.is_nan() || slow.is_nan() => None,
+                // a flat market keeps the base bandwidth
+                (fast, slow) if fast == 0.0 && slow == 0.0 => Some(1.0),
+                (fast, slow) => Some(fast / slow),
+            })
+            .collect())
+    }
+
+    /// The bandwidth of every bar for a base `look_back` and the volatility ratios.
+    pub(crate) fn look_back_values(&self, look_back: f64, ratio: &[Option<f64>]) -> Vec<Option<f64>> {
+        ratio.iter().map(|r| r.map(|r| (look_back / r).clamp(self.min_look_back, self.max_look_back))).collect()
+    }
+
+    /// Calculates the bandwidth of every bar.
+    ///
+    /// # Arguments
+    ///
+    /// * `df` - The DataFrame with the source column, and the high, low and close columns for the ATR.
+    /// * `source` - The source column.
+    /// * `look_back` - The bandwidth when the fast and slow volatilities are the same.
+    ///
+    /// # Returns
+    ///
+    /// The `look_back` series, null until the slow volatility is known.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use polars::prelude::*;
+    /// use tech_analysis::{AdaptiveBandwidth, VolatilityMeasure};
+    /// let df = df!("close" => (0..80).map(|i| (i as f64 / 5.0).sin() * i as f64).collect::<Vec<f64>>()).unwrap();
+    /// let bandwidth = AdaptiveBandwidth { measure: VolatilityMeasure::Stdev, ..Default::default() };
+    /// println!("{:?}", bandwidth.look_back(&df, "close", 8.0).unwrap());
+    /// ```
+    pub fn look_back(&self, df: &DataFrame, source: &str, look_back: f64) -> Result<Series, Box<dyn std::error::Error>> {
+        self.configuration_check();
+        let ratio = self.frame_ratio(df, source)?;
+        Ok(Series::new("look_back", self.look_back_values(look_back, &ratio)))
+    }
+
+    /// The volatility ratio of the bars of a DataFrame.
+    fn frame_ratio(&self, df: &DataFrame, source: &str) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+        let src = helper::column_values(df, source)?;
+        match self.measure {
+            VolatilityMeasure::Atr => {
+                let high = helper::column_values(df, "high")?;
+                let low = helper::column_values(df, "low")?;
+                let close = helper::column_values(df, "close")?;
+                self.volatility_ratio(&src, Some((&high, &low, &close)))
+            }
+            VolatilityMeasure::Stdev => self.volatility_ratio(&src, None),
+        }
+    }
+}
+
+/// The kernel estimates with the bandwidth of every bar, `None` where the bar has no bandwidth or no value.
+pub(crate) fn adaptive_values<K, F>(values: &[Option<f64>], look_backs: &[Option<f64>], kernel: F, size: usize) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>>
+where
+    K: Kernel,
+    F: Fn(f64) -> K,
+{
+    if size == 0 || values.len() < size {
+        return Err(format!("at least {} values are required by the kernel, got {}", size, values.len()).into());
+    }
+    if values.len() != look_backs.len() {
+        return Err(format!("{} bandwidths for {} values", look_backs.len(), values.len()).into());
+    }
+    let mut kernel_line = vec![None; values.len()];
+    for bar_index in size - 1..values.len() {
+        let Some(look_back) = look_backs[bar_index] else { continue };
+        let kernel = kernel(look_back);
+        let (current_weight, cumulative_weight) = values[bar_index + 1 - size..=bar_index]
+            .iter()
+            .rev()
+            .enumerate()
+            .filter_map(|(distance, y)| y.map(|y| (y, kernel.weight(distance))))
+            .fold((0.0, 0.0), |(c, t), (y, w)| (c + y * w, t + w));
+        kernel_line[bar_index] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
+    }
+    Ok(kernel_line)
+}
+
+/// Calculates the kernel regression of a series with a bandwidth for every bar.
+///
+/// Same window as [`crate::kernel_regression`], the last `start_at_bar + 2` values, weighted by the kernel
+/// built from the bandwidth of the bar. The bars without a bandwidth are null, nulls and `NaN` values are skipped.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `look_back` - The bandwidth of every bar, e.g. from [`AdaptiveBandwidth::look_back`].
+/// * `kernel` - Builds the kernel of a bandwidth.
+/// * `start_at_bar` - The starting point for the calculation.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{adaptive_kernel_regression, LaplaceKernel};
+/// let src = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let look_back = Series::new("look_back", vec![2.0, 2.0, 1.0, 4.0, 8.0]);
+/// let result = adaptive_kernel_regression(&src, &look_back, |look_back| LaplaceKernel { look_back }, 1).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn adaptive_kernel_regression<K, F>(src: &Series, look_back: &Series, kernel: F, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>>
+where
+    K: Kernel,
+    F: Fn(f64) -> K,
+{
+    if start_at_bar < 0 {
+        return Err("start_at_bar must not be negative".into());
+    }
+    let values = kernel_values(src, NanPolicy::default())?;
+    let look_backs = helper::series_options(look_back)?;
+    Ok(Series::new(src.name(), adaptive_values(&values, &look_backs, kernel, (start_at_bar + 2) as usize)?))
+}
+
+/// Same as [`crate::rational_quadratic`] with the bandwidth adapting to the volatility.
+///
+/// # Arguments
+///
+/// * `df` - The DataFrame with the source column, and the high, low and close columns for the ATR.
+/// * `source` - The source column.
+/// * `look_back` - The bandwidth when the fast and slow volatilities are the same.
+/// * `relative_weight` - The relative weight of the time frames.
+/// * `start_at_bar` - The starting point for the calculation.
+/// * `bandwidth` - The volatility measure and the bounds of the bandwidth.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{rational_quadratic_adaptive, AdaptiveBandwidth};
+/// let close: Vec<f64> = (0..80).map(|i| 100.0 + (i as f64 / 5.0).sin() * i as f64).collect();
+/// let df = df!(
+///     "high" => close.iter().map(|c| c + 1.0).collect::<Vec<f64>>(),
+///     "low" => close.iter().map(|c| c - 1.0).collect::<Vec<f64>>(),
+///     "close" => close
+/// ).unwrap();
+/// let result = rational_quadratic_adaptive(&df, "close", 8, 8.0, 25, &AdaptiveBandwidth::default()).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn rational_quadratic_adaptive(df: &DataFrame, source: &str, look_back: i32, relative_weight: f32, start_at_bar: i32, bandwidth: &AdaptiveBandwidth) -> Result<Series, Box<dyn std::error::Error>> {
+    let relative_weight = relative_weight as f64;
+    adaptive_kernel_regression(df.column(source)?, &bandwidth.look_back(df, source, look_back as f64)?, |look_back| RationalQuadraticKernel { look_back, relative_weight }, start_at_bar)
+}
+
+/// Same as [`crate::gaussian`] with the bandwidth adapting to the volatility.
+///
+/// # Arguments
+///
+/// * `df` - The DataFrame with the source column, and the high, low and close columns for the ATR.
+/// * `source` - The source column.
+/// * `look_back` - The bandwidth when the fast and slow volatilities are the same.
+/// * `start_at_bar` - The starting point for the calculation.
+/// * `bandwidth` - The volatility measure and the bounds of the bandwidth.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{gaussian_adaptive, AdaptiveBandwidth, VolatilityMeasure};
+/// let df = df!("close" => (0..80).map(|i| (i as f64 / 5.0).sin() * i as f64).collect::<Vec<f64>>()).unwrap();
+/// let bandwidth = AdaptiveBandwidth { measure: VolatilityMeasure::Stdev, ..Default::default() };
+/// let result = gaussian_adaptive(&df, "close", 8, 25, &bandwidth).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn gaussian_adaptive(df: &DataFrame, source: &str, look_back: i32, start_at_bar: i32, bandwidth: &AdaptiveBandwidth) -> Result<Series, Box<dyn std::error::Error>> {
+    adaptive_kernel_regression(df.column(source)?, &bandwidth.look_back(df, source, look_back as f64)?, |look_back| GaussianKernel { look_back }, start_at_bar)
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use crate::lorentzian_classification::kernel::{gaussian, rational_quadratic};
+
+    #[test]
+    fn test_look_back() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let bandwidth = AdaptiveBandwidth::default();
+        let look_back: Vec<Option<f64>> = bandwidth.look_back(&df, "close", 8.0)?.f64()?.into_iter().collect();
+        // unknown until the slow atr is known, then always within the bounds
+        assert!(look_back[..49].iter().all(|l| l.is_none()));
+        assert!(look_back[49..].iter().all(|l| l.is_some_and(|l| (2.0..=32.0).contains(&l))));
+        // the bandwidth changes with the volatility
+        let distinct = look_back[49..].iter().flatten().fold(Vec::<f64>::new(), |mut seen, l| {
+            if !seen.contains(l) { seen.push(*l) }
+            seen
+        });
+        assert!(distinct.len() > 100);
+        // a fast market is smoothed less, a quiet one more
+        let bounded = bandwidth.look_back_values(8.0, &[Some(4.0), Some(0.5), Some(0.0), Some(f64::INFINITY), None]);
+        assert_eq!(bounded, vec![Some(2.0), Some(16.0), Some(32.0), Some(2.0), None]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptive_kernels() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        // a constant bandwidth is the kernel regression
+        let constant = Series::new("look_back", vec![8.0; close.len()]);
+        let adaptive = adaptive_kernel_regression(close, &constant, |look_back| RationalQuadraticKernel { look_back, relative_weight: 8.0 }, 25)?;
+        let fixed = rational_quadratic(close, 8, 8.0, 25)?;
+        for (a, b) in adaptive.f64()?.into_iter().zip(fixed.f64()?) {
+            match (a, b) {
+                (Some(a), Some(b)) => assert!((a - b).abs() <= 1e-9 * b.abs()),
+                (a, b) => assert_eq!(a, b),
+            }
+        }
+        // bounds equal to the base bandwidth fix it
+        let pinned = AdaptiveBandwidth { min_look_back: 6.0, max_look_back: 6.0, ..Default::default() };
+        let line = gaussian_adaptive(&df, "close", 6, 25, &pinned)?;
+        let fixed = gaussian(close, 6, 25)?;
+        assert_eq!(line.null_count(), 49);
+        for (a, b) in line.f64()?.into_iter().zip(fixed.f64()?).skip(49) {
+            assert!((a.unwrap() - b.unwrap()).abs() <= 1e-9 * b.unwrap().abs());
+        }
+        // causal and deterministic, a later bar never changes an earlier estimate
+        let bandwidth = AdaptiveBandwidth { measure: VolatilityMeasure::Stdev, ..Default::default() };
+        let full = rational_quadratic_adaptive(&df, "close", 8, 8.0, 25, &bandwidth)?;
+        let head = rational_quadratic_adaptive(&df.slice(0, 300), "close", 8, 8.0, 25, &bandwidth)?;
+        assert!(full.slice(0, 300).series_equal_missing(&head));
+        assert!(full.series_equal_missing(&rational_quadratic_adaptive(&df, "close", 8, 8.0, 25, &bandwidth)?));
+        assert!(gaussian_adaptive(&df.select(["close"])?, "close", 8, 25, &AdaptiveBandwidth::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_configuration_check() {
+        AdaptiveBandwidth { min_look_back: 10.0, max_look_back: 5.0, ..Default::default() }.configuration_check();
+    }
+}
@@ -0,0 +1,204 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/11 14:02
+ * @Email: uyplayer@qq.com
+ * @File: ann
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! approximate nearest neighbours search under lorentzian distance
+
+use std::collections::VecDeque;
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::types::{ConfigCheck, Settings};
+
+
+/// The Lorentzian distance between the feature vectors of two bars, `sum(ln(1 + |x - y|))`.
+///
+/// # Arguments
+///
+/// * `features` - The feature columns.
+/// * `a` - The index of the first bar.
+/// * `b` - The index of the second bar.
+pub fn lorentzian_distance(features: &[Vec<f64>], a: usize, b: usize) -> f64 {
+    features.iter().map(|f| (1.0 + (f[a] - f[b]).abs()).ln()).sum()
+}
+
+/// The neighbours kept by the search at a bar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbors {
+    /// The bar indices of the neighbours, oldest selection first.
+    pub indices: Vec<usize>,
+    /// The Lorentzian distances of the neighbours to the bar.
+    pub distances: Vec<f64>,
+    /// The training labels of the neighbours.
+    pub labels: Vec<i32>,
+    /// The sum of the labels of the neighbours.
+    pub prediction: f64,
+}
+
+/// Approximate nearest neighbours search under the Lorentzian distance.
+/// same semantics as the tradingview script
+///
+/// * only the last `max_bars_back` bars are predicted, earlier bars keep a zero prediction
+/// * the candidates are the first `max_bars_back` training bars, skipping every 4th bar for chronological spacing
+/// * a candidate is kept when its distance is not below the last kept distance
+/// * when more than `neighbors_count` neighbours are kept, the oldest is dropped and the last distance
+///   is raised to the distance at the 75th percentile
+/// * the neighbours are carried over from one bar to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LorentzianAnn {
+    /// The number of neighbours to keep.
+    pub neighbors_count: usize,
+    /// The maximum number of bars to look back.
+    pub max_bars_back: usize,
+}
+
+impl ConfigCheck for LorentzianAnn {
+    type Output = ();
+    fn configuration_check(&self) -> Self::Output {
+        if self.neighbors_count == 0 {
+            panic!("neighbors_count must be bigger than zero");
+        }
+        if self.max_bars_back == 0 {
+            panic!("max_bars_back must be bigger than zero");
+        }
+    }
+}
+
+impl LorentzianAnn {
+    /// Creates the search from `settings.neighbors_count` and `settings.max_bars_back`.
+    pub fn new(settings: &Settings) -> Self {
+        settings.configuration_check();
+        LorentzianAnn {
+            neighbors_count: settings.neighbors_count as usize,
+            max_bars_back: settings.max_bars_back as usize,
+        }
+    }
+
+    /// Searches the neighbours of every bar.
+    ///
+    /// # Arguments
+    ///
+    /// * `features` - The feature columns, see [`crate::feature_frame`].
+    /// * `labels` - The training label of every bar, as `Direction` values.
+    ///
+    /// # Returns
+    ///
+    /// The neighbours of every bar.
+    pub fn search(&self, features: &[Vec<f64>], labels: &[i32]) -> Vec<Neighbors> {
+        self.configuration_check();
+        let n = labels.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let max_bars_back_index = (n - 1).saturating_sub(self.max_bars_back);
+        // (index, distance, label) of the kept neighbours
+        let mut kept: VecDeque<(usize, f64, i32)> = VecDeque::new();
+        let mut result = Vec::with_capacity(n);
+        let mut last = Neighbors { indices: Vec::new(), distances: Vec::new(), labels: Vec::new(), prediction: 0.0 };
+        for bar_index in 0..n {
+            if bar_index >= max_bars_back_index {
+                let mut last_distance = -1.0;
+                let size_loop = (self.max_bars_back - 1).min(bar_index);
+                for (i, &label) in labels.iter().enumerate().take(size_loop + 1) {
+                    let d = lorentzian_distance(features, bar_index, i);
+                    if d >= last_distance && i % 4 != 0 {
+                        last_distance = d;
+                        kept.push_back((i, d, label));
+                        if kept.len() > self.neighbors_count {
+                            last_distance = kept[(self.neighbors_count as f64 * 3.0 / 4.0).round() as usize].1;
+                            kept.pop_front();
+                        }
+                    }
+                }
+                last = Neighbors {
+                    indices: kept.iter().map(|k| k.0).collect(),
+                    distances: kept.iter().map(|k| k.1).collect(),
+                    labels: kept.iter().map(|k| k.2).collect(),
+                    prediction: kept.iter().map(|k| k.2).sum::<i32>() as f64,
+                };
+            }
+            result.push(last.clone());
+        }
+        result
+    }
+
+    /// Calculates the prediction of every bar.
+    ///
+    /// # Arguments
+    ///
+    /// * `features` - The feature DataFrame, see [`crate::feature_frame`].
+    /// * `labels` - The training labels, as `Direction` values.
+    ///
+    /// # Returns
+    ///
+    /// The `prediction` series, the sum of the neighbour labels of every bar.
+    pub fn predict(&self, features: &DataFrame, labels: &Series) -> Result<Series, Box<dyn std::error::Error>> {
+        let features = features
+            .get_columns()
+            .iter()
+            .map(helper::series_values)
+            .collect::<Result<Vec<Vec<f64>>, Box<dyn std::error::Error>>>()?;
+        let labels: Vec<i32> = labels.cast(&DataType::Int32)?.i32()?.into_iter().map(|v| v.unwrap_or(0)).collect();
+        if features.iter().any(|f| f.len() != labels.len()) {
+            return Err("features and labels must have the same length".into());
+        }
+        let prediction: Vec<f64> = self.search(&features, &labels).iter().map(|n| n.prediction).collect();
+        Ok(Series::new("prediction", prediction))
+    }
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lorentzian_distance() {
+        let features = vec![vec![0.0, 1.0], vec![0.0, 0.5]];
+        let d = lorentzian_distance(&features, 0, 1);
+        assert!((d - (2.0f64.ln() + 1.5f64.ln())).abs() < 1e-12);
+        assert_eq!(lorentzian_distance(&features, 1, 1), 0.0);
+    }
+
+    #[test]
+    fn test_search() {
+        let features = vec![(0..40).map(|i| (i as f64 / 3.0).sin()).collect::<Vec<f64>>()];
+        let labels: Vec<i32> = (0..40).map(|i| if i % 3 == 0 { 1 } else { -1 }).collect();
+        let ann = LorentzianAnn { neighbors_count: 4, max_bars_back: 20 };
+        let neighbors = ann.search(&features, &labels);
+        assert_eq!(neighbors.len(), 40);
+        // bars before the max bars back window are not predicted
+        assert!(neighbors[..19].iter().all(|n| n.indices.is_empty() && n.prediction == 0.0));
+        for n in &neighbors[19..] {
+            assert!(!n.indices.is_empty() && n.indices.len() <= 4);
+            assert!(n.indices.iter().all(|i| i % 4 != 0 && *i < 20));
+            assert_eq!(n.labels, n.indices.iter().map(|&i| labels[i]).collect::<Vec<i32>>());
+            assert_eq!(n.prediction, n.labels.iter().sum::<i32>() as f64);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "max_bars_back must be bigger than zero")]
+    fn test_search_without_max_bars_back() {
+        LorentzianAnn { neighbors_count: 8, max_bars_back: 0 }.search(&[vec![0.0, 1.0]], &[1, -1]);
+    }
+
+    #[test]
+    fn test_predict() -> Result<(), Box<dyn std::error::Error>> {
+        let features = df!("f1" => (0..30).map(|i| (i as f64).cos()).collect::<Vec<f64>>())?;
+        let labels = Series::new("labels", (0..30).map(|i| if i % 2 == 0 { 1 } else { -1 }).collect::<Vec<i32>>());
+        let ann = LorentzianAnn { neighbors_count: 8, max_bars_back: 2000 };
+        let prediction = ann.predict(&features, &labels)?;
+        assert_eq!(prediction.len(), 30);
+        assert!(ann.predict(&features, &labels.slice(0, 10)).is_err());
+        Ok(())
+    }
+}
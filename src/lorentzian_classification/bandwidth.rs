@@ -0,0 +1,203 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/25 10:20
+ * @Email: uyplayer@qq.com
+ * @File: bandwidth
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! kernel bandwidth selection by cross-validation of the causal estimator
+
+use polars::prelude::*;
+use crate::lorentzian_classification::regression::{kernel_values, kernel_weights, Kernel, NanPolicy};
+
+
+/// The score minimized by [`select_bandwidth`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum CrossValidation {
+    /// Mean squared error of the estimates leaving the current bar out of its own window,
+    /// which is the prediction of every bar from the bars before it.
+    #[default]
+    LeaveOneOut,
+    /// Generalized cross-validation, the mean squared residual divided by `(1 - mean(h))^2`
+    /// where `h` is the weight of the current bar in its own estimate.
+    Generalized,
+}
+
+/// The result of [`select_bandwidth`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandwidthSelection<K> {
+    /// The candidate with the lowest score.
+    pub best: K,
+    /// The score of the best candidate.
+    pub best_score: f64,
+    /// Every candidate with its score, in the order of the candidates.
+    /// Candidates without any scored bar have no score.
+    pub scores: Vec<(K, Option<f64>)>,
+}
+
+/// Scores a kernel over the bars with a full window, `None` when no bar could be scored.
+fn kernel_score<K: Kernel + ?Sized>(values: &[Option<f64>], kernel: &K, size: usize, criterion: CrossValidation) -> Option<f64> {
+    let weights = kernel_weights(kernel, size);
+    let (mut squared_error, mut leverage, mut scored) = (0.0, 0.0, 0usize);
+    for bar_index in size - 1..values.len() {
+        let Some(y) = values[bar_index] else { continue };
+        let (current_weight, cumulative_weight) = values[bar_index + 1 - size..=bar_index]
+            .iter()
+            .rev()
+            .zip(&weights)
+            .filter_map(|(v, w)| v.map(|v| (v * w, *w)))
+            .fold((0.0, 0.0), |(c, t), (v, w)| (c + v, t + w));
+        let residual = match criterion {
+            CrossValidation::LeaveOneOut => {
+                let rest = cumulative_weight - weights[0];
+                if rest <= 0.0 {
+                    continue;
+                }
+                y - (current_weight - y * weights[0]) / rest
+            }
+            CrossValidation::Generalized => {
+                if cumulative_weight <= 0.0 {
+                    continue;
+                }
+                leverage += weights[0] / cumulative_weight;
+                y - current_weight / cumulative_weight
+            }
+        };
+        squared_error += residual * residual;
+        scored += 1;
+    }
+    if scored == 0 {
+        return None;
+    }
+    let n = scored as f64;
+    match criterion {
+        CrossValidation::LeaveOneOut => Some(squared_error / n),
+        CrossValidation::Generalized => Some(squared_error / n / (1.0 - leverage / n).powi(2)),
+    }
+}
+
+/// Selects the kernel parameters by cross-validation.
+///
+/// Every candidate kernel is scored on the causal estimator of [`crate::kernel_regression`], the window of
+/// the last `start_at_bar + 2` values, over the bars with a full window. Nulls and `NaN` values are skipped
+/// as in the kernel functions. The parameters are the fields of the kernel, so any kernel of
+/// [`crate::kernel_regression`] can be tuned, e.g. `look_back` and `relative_weight` of a [`crate::RationalQuadraticKernel`].
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `candidates` - The kernels to score.
+/// * `start_at_bar` - The starting point for the calculation.
+/// * `criterion` - Leave-one-out or generalized cross-validation.
+///
+/// # Returns
+///
+/// The best candidate and the score curve of all the candidates, an error when no candidate could be scored.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{select_bandwidth, CrossValidation, RationalQuadraticKernel};
+/// let src = Series::new("data", (0..200).map(|i| (i as f64 / 10.0).sin()).collect::<Vec<f64>>());
+/// let candidates: Vec<RationalQuadraticKernel> = [2.0, 4.0, 8.0, 16.0]
+///     .into_iter()
+///     .flat_map(|look_back| [1.0, 8.0].map(|relative_weight| RationalQuadraticKernel { look_back, relative_weight }))
+///     .collect();
+/// let selection = select_bandwidth(&src, &candidates, 25, CrossValidation::LeaveOneOut).unwrap();
+/// println!("{:?} {}", selection.best, selection.best_score);
+/// ```
+pub fn select_bandwidth<K: Kernel + Clone>(src: &Series, candidates: &[K], start_at_bar: i32, criterion: CrossValidation) -> Result<BandwidthSelection<K>, Box<dyn std::error::Error>> {
+    if start_at_bar < 0 {
+        return Err("start_at_bar must not be negative".into());
+    }
+    if candidates.is_empty() {
+        return Err("at least one candidate kernel is required".into());
+    }
+    let size = (start_at_bar + 2) as usize;
+    let values = kernel_values(src, NanPolicy::Skip)?;
+    if values.len() < size {
+        return Err(format!("at least {} values are required by the kernel, got {}", size, values.len()).into());
+    }
+    let scores: Vec<(K, Option<f64>)> = candidates
+        .iter()
+        .map(|kernel| (kernel.clone(), kernel_score(&values, kernel, size, criterion)))
+        .collect();
+    let (best, best_score) = scores
+        .iter()
+        .filter_map(|(kernel, score)| score.filter(|score| !score.is_nan()).map(|score| (kernel, score)))
+        .fold(None, |best: Option<(&K, f64)>, candidate| match best {
+            Some(best) if best.1 <= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+        .map(|(kernel, score)| (kernel.clone(), score))
+        .ok_or("no candidate could be scored")?;
+    Ok(BandwidthSelection { best, best_score, scores })
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use crate::lorentzian_classification::regression::{kernel_regression, EpanechnikovKernel, GaussianKernel, LaplaceKernel, LocallyPeriodicKernel, PeriodicKernel, RationalQuadraticKernel};
+
+    #[test]
+    fn test_leave_one_out() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let close = df.column("close")?.slice(0, 300);
+        let values: Vec<f64> = close.f64()?.into_no_null_iter().collect();
+        let kernel = GaussianKernel { look_back: 6.0 };
+        let selection = select_bandwidth(&close, &[kernel], 25, CrossValidation::LeaveOneOut)?;
+        // the leave-one-out estimate of a bar is the kernel regression with the bar missing
+        let mut squared_error = 0.0;
+        for bar_index in 26..values.len() {
+            let mut dropped: Vec<Option<f64>> = values.iter().map(|v| Some(*v)).collect();
+            dropped[bar_index] = None;
+            let estimate = kernel_regression(&Series::new("close", dropped), &kernel, 25)?.f64()?.get(bar_index).unwrap();
+            squared_error += (values[bar_index] - estimate).powi(2);
+        }
+        let expected = squared_error / (values.len() - 26) as f64;
+        assert!((selection.best_score - expected).abs() <= expected * 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_bandwidth() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        let candidates: Vec<GaussianKernel> = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0].map(|look_back| GaussianKernel { look_back }).to_vec();
+        for criterion in [CrossValidation::LeaveOneOut, CrossValidation::Generalized] {
+            let selection = select_bandwidth(close, &candidates, 25, criterion)?;
+            assert_eq!(selection.scores.len(), candidates.len());
+            assert!(selection.scores.iter().all(|(_, score)| score.unwrap() >= selection.best_score));
+            // the price trends, the widest window is never the best
+            assert!(selection.best.look_back < 32.0);
+        }
+        // every kernel can be tuned
+        let rational: Vec<RationalQuadraticKernel> = [4.0, 8.0]
+            .into_iter()
+            .flat_map(|look_back| [1.0, 8.0].map(|relative_weight| RationalQuadraticKernel { look_back, relative_weight }))
+            .collect();
+        assert_eq!(select_bandwidth(close, &rational, 25, CrossValidation::Generalized)?.scores.len(), 4);
+        select_bandwidth(close, &[PeriodicKernel { look_back: 8.0, period: 24.0 }], 25, CrossValidation::LeaveOneOut)?;
+        select_bandwidth(close, &[LocallyPeriodicKernel { look_back: 8.0, period: 24.0 }], 25, CrossValidation::LeaveOneOut)?;
+        select_bandwidth(close, &[LaplaceKernel { look_back: 8.0 }], 25, CrossValidation::LeaveOneOut)?;
+        // an epanechnikov kernel narrower than a bar only weighs the current bar, it can not predict it
+        let epanechnikov = [EpanechnikovKernel { look_back: 1.0 }, EpanechnikovKernel { look_back: 8.0 }];
+        let selection = select_bandwidth(close, &epanechnikov, 25, CrossValidation::LeaveOneOut)?;
+        assert_eq!(selection.scores[0].1, None);
+        assert_eq!(selection.best, epanechnikov[1]);
+        let error = select_bandwidth(close, &epanechnikov[..1], 25, CrossValidation::LeaveOneOut).unwrap_err();
+        assert_eq!(error.to_string(), "no candidate could be scored");
+        assert!(select_bandwidth(close, &[] as &[GaussianKernel], 25, CrossValidation::LeaveOneOut).is_err());
+        assert!(select_bandwidth(&close.slice(0, 10), &candidates, 25, CrossValidation::LeaveOneOut).is_err());
+        Ok(())
+    }
+}
@@ -12,10 +12,380 @@
 
 //! main entrance for lorentzian classification
 
-use polars::chunked_array::Settings;
-use crate::lorentzian_classification::{kernel, types, helper};
+use polars::prelude::*;
+use crate::lorentzian_classification::{helper, features};
+use crate::lorentzian_classification::ann::LorentzianAnn;
+use crate::lorentzian_classification::labels::{FixedHorizonLabeler, Labeler};
+use crate::lorentzian_classification::trade::{trade_events, ExitMode, TradeBar, TradeEventKind};
+use crate::lorentzian_classification::types::{ConfigCheck, Direction, Filters, KernelFilter, Settings};
 
 
-pub fn my_classification(){
-    println!("{}","hallo");
+/// Runs the Lorentzian Classification strategy over an OHLCV DataFrame.
+/// port of the tradingview script by jdehorty
+///
+/// The DataFrame must contain `open`, `high`, `low`, `close` and the `settings.source` column.
+/// Every bar is classified by the approximate nearest neighbours of its feature vector
+/// (the first `settings.feature_count` of `settings.features`) under the Lorentzian distance,
+/// the prediction is gated by the volatility, regime and ADX filters, the EMA/SMA trend
+/// filters and the kernel regression filter, and trades are closed with fixed or dynamic exits.
+///
+/// # Arguments
+///
+/// * `df` - The input OHLCV DataFrame.
+/// * `settings` - The general settings of the classifier.
+/// * `filters` - The volatility, regime and ADX filters.
+/// * `kernel` - The kernel regression filter.
+///
+/// # Returns
+///
+/// The input DataFrame with the `prediction`, `signal`, `start_long`, `start_short`, `end_long`
+/// and `end_short` columns appended, and `kernel_estimate` if `kernel.show_kernel_estimate` is set.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{lorentzian_classification, Settings, Filters, KernelFilter, DEFAULT_FEATURES};
+/// let close: Vec<f64> = (0..200).map(|i| 100.0 + (i as f64 / 5.0).sin() * 10.0).collect();
+/// let df = df!(
+///     "open" => close.iter().map(|c| c - 0.5).collect::<Vec<f64>>(),
+///     "high" => close.iter().map(|c| c + 1.0).collect::<Vec<f64>>(),
+///     "low" => close.iter().map(|c| c - 1.0).collect::<Vec<f64>>(),
+///     "close" => close.clone()
+/// ).unwrap();
+/// let settings = Settings { source: "close", neighbors_count: 8, max_bars_back: 2000, feature_count: 5,
+///     features: DEFAULT_FEATURES, show_exits: false,
+///     use_dynamic_exits: false, use_ema_filter: false, ema_period: 200, use_sma_filter: false, sma_period: 200 };
+/// let filters = Filters { use_volatility_filter: true, use_regime_filter: true, use_adx_filter: false,
+///     regime_threshold: -0.1, adx_threshold: 20 };
+/// let kernel = KernelFilter { use_kernel_filter: true, show_kernel_estimate: true, use_kernel_smoothing: false,
+///     look_back_window: 8, relative_weight: 8.0, regression_level: 25.0, crossover_lag: 2, adaptive_bandwidth: None };
+/// let result = lorentzian_classification(&df, &settings, &filters, &kernel).unwrap();
+/// println!("{:?}", result.column("signal"));
+/// ```
+pub fn lorentzian_classification(df: &DataFrame, settings: &Settings, filters: &Filters, kernel: &KernelFilter) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    lorentzian_classification_with_labeler(df, settings, filters, kernel, &FixedHorizonLabeler::default())
+}
+
+/// Runs the Lorentzian Classification strategy with custom training labels.
+///
+/// Same as [`lorentzian_classification`], but the neighbours are labelled by `labeler`
+/// instead of the fixed 4 bars horizon of the tradingview script.
+///
+/// # Arguments
+///
+/// * `df` - The input OHLCV DataFrame.
+/// * `settings` - The general settings of the classifier.
+/// * `filters` - The volatility, regime and ADX filters.
+/// * `kernel` - The kernel regression filter.
+/// * `labeler` - The generator of the training labels, applied to the `settings.source` column.
+///
+/// # Returns
+///
+/// The input DataFrame with the classification columns appended.
+pub fn lorentzian_classification_with_labeler(df: &DataFrame, settings: &Settings, filters: &Filters, kernel: &KernelFilter, labeler: &dyn Labeler) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    settings.configuration_check();
+    filters.configuration_check();
+    kernel.configuration_check();
+
+    let open = helper::column_values(df, "open")?;
+    let high = helper::column_values(df, "high")?;
+    let low = helper::column_values(df, "low")?;
+    let close = helper::column_values(df, "close")?;
+    let src = helper::column_values(df, settings.source)?;
+    let n = src.len();
+
+    // feature space, training labels and predictions
+    let features = feature_space(df, settings)?;
+    let labels: Vec<i32> = labeler.directions(&src).into_iter().map(|d| d as i32).collect();
+    let prediction: Vec<f64> = LorentzianAnn::new(settings)
+        .search(&features, &labels)
+        .iter()
+        .map(|neighbors| neighbors.prediction)
+        .collect();
+
+    // filters
+    let filter_all = filters.mask(&open, &high, &low, &close, &src);
+
+    let mut signal = vec![Direction::NEUTRAL; n];
+    for i in 0..n {
+        signal[i] = if prediction[i] > 0.0 && filter_all[i] {
+            Direction::LONG
+        } else if prediction[i] < 0.0 && filter_all[i] {
+            Direction::SHORT
+        } else if i > 0 {
+            signal[i - 1]
+        } else {
+            Direction::NEUTRAL
+        };
+    }
+
+    // ema and sma trend filters
+    let (is_uptrend, is_downtrend) = settings.trend_mask(&close);
+
+    // kernel regression
+    let kernel_signals = kernel.signal_values(&src, Some((&high, &low, &close)))?;
+    let (is_bullish, is_bearish) = (&kernel_signals.is_bullish, &kernel_signals.is_bearish);
+
+    // entries
+    let mut start_long = vec![false; n];
+    let mut start_short = vec![false; n];
+    for i in 0..n {
+        let is_different_signal = i > 0 && signal[i] != signal[i - 1];
+        let is_new_buy = signal[i] == Direction::LONG && is_uptrend[i] && is_different_signal;
+        let is_new_sell = signal[i] == Direction::SHORT && is_downtrend[i] && is_different_signal;
+        start_long[i] = is_new_buy && is_bullish[i] && is_uptrend[i];
+        start_short[i] = is_new_sell && is_bearish[i] && is_downtrend[i];
+    }
+
+    // exits
+    let is_dynamic_exit_valid = !settings.use_ema_filter && !settings.use_sma_filter && !kernel.use_kernel_smoothing;
+    let exit_mode = if settings.use_dynamic_exits && is_dynamic_exit_valid { ExitMode::Dynamic } else { ExitMode::default() };
+    let bars: Vec<TradeBar> = (0..n)
+        .map(|i| TradeBar {
+            signal: signal[i],
+            start_long: start_long[i],
+            start_short: start_short[i],
+            bullish_change: kernel_signals.is_bullish_change[i],
+            bearish_change: kernel_signals.is_bearish_change[i],
+        })
+        .collect();
+    let mut end_long = vec![false; n];
+    let mut end_short = vec![false; n];
+    for event in trade_events(&bars, exit_mode) {
+        match event.kind {
+            TradeEventKind::Exit(reason) if reason.is_plotted() && event.direction == Direction::LONG => end_long[event.bar_index] = true,
+            TradeEventKind::Exit(reason) if reason.is_plotted() => end_short[event.bar_index] = true,
+            _ => {}
+        }
+    }
+
+    let mut result = df.clone();
+    result.with_column(Series::new("prediction", prediction))?;
+    result.with_column(Series::new("signal", signal.iter().map(|&d| d as i32).collect::<Vec<i32>>()))?;
+    result.with_column(Series::new("start_long", start_long))?;
+    result.with_column(Series::new("start_short", start_short))?;
+    result.with_column(Series::new("end_long", end_long))?;
+    result.with_column(Series::new("end_short", end_short))?;
+    if kernel.show_kernel_estimate {
+        result.with_column(Series::new("kernel_estimate", helper::nan_to_null(&kernel_signals.yhat1)))?;
+    }
+    Ok(result)
+}
+
+/// The feature columns selected by `settings`.
+fn feature_space(df: &DataFrame, settings: &Settings) -> Result<Vec<Vec<f64>>, Box<dyn std::error::Error>> {
+    settings.features[..settings.feature_count as usize]
+        .iter()
+        .map(|feature| features::feature_values(df, feature))
+        .collect()
+}
+
+/// Why the classifier predicted a bar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// The index of the bar.
+    pub bar_index: usize,
+    /// The raw prediction, the sum of the neighbour labels.
+    pub prediction: f64,
+    /// The prediction divided by `settings.neighbors_count`, in `[-1, 1]`.
+    pub confidence: f64,
+    /// The bar indices of the neighbours, oldest selection first.
+    pub neighbor_indices: Vec<usize>,
+    /// The Lorentzian distances of the neighbours to the bar.
+    pub neighbor_distances: Vec<f64>,
+    /// The training labels of the neighbours.
+    pub neighbor_labels: Vec<i32>,
+    /// The feature vector of the bar.
+    pub features: Vec<f64>,
+}
+
+/// Explains the prediction of every bar.
+///
+/// The predictions are the same as the `prediction` column of [`lorentzian_classification_with_labeler`],
+/// the filters and the kernel regression do not change them.
+///
+/// # Arguments
+///
+/// * `df` - The input OHLCV DataFrame.
+/// * `settings` - The general settings of the classifier.
+/// * `labeler` - The generator of the training labels, applied to the `settings.source` column.
+///
+/// # Returns
+///
+/// The explanation of every bar.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{explain_classification, FixedHorizonLabeler, Settings, DEFAULT_FEATURES};
+/// let close: Vec<f64> = (0..200).map(|i| 100.0 + (i as f64 / 5.0).sin() * 10.0).collect();
+/// let df = df!(
+///     "open" => close.iter().map(|c| c - 0.5).collect::<Vec<f64>>(),
+///     "high" => close.iter().map(|c| c + 1.0).collect::<Vec<f64>>(),
+///     "low" => close.iter().map(|c| c - 1.0).collect::<Vec<f64>>(),
+///     "close" => close.clone()
+/// ).unwrap();
+/// let settings = Settings { source: "close", neighbors_count: 8, max_bars_back: 2000, feature_count: 5,
+///     features: DEFAULT_FEATURES, show_exits: false,
+///     use_dynamic_exits: false, use_ema_filter: false, ema_period: 200, use_sma_filter: false, sma_period: 200 };
+/// let explanations = explain_classification(&df, &settings, &FixedHorizonLabeler::default()).unwrap();
+/// println!("{:?}", explanations.last());
+/// ```
+pub fn explain_classification(df: &DataFrame, settings: &Settings, labeler: &dyn Labeler) -> Result<Vec<Explanation>, Box<dyn std::error::Error>> {
+    settings.configuration_check();
+    let src = helper::column_values(df, settings.source)?;
+    let features = feature_space(df, settings)?;
+    let labels: Vec<i32> = labeler.directions(&src).into_iter().map(|d| d as i32).collect();
+    let explanations = LorentzianAnn::new(settings)
+        .search(&features, &labels)
+        .into_iter()
+        .enumerate()
+        .map(|(bar_index, neighbors)| Explanation {
+            bar_index,
+            prediction: neighbors.prediction,
+            confidence: neighbors.prediction / settings.neighbors_count as f64,
+            neighbor_indices: neighbors.indices,
+            neighbor_distances: neighbors.distances,
+            neighbor_labels: neighbors.labels,
+            features: features.iter().map(|f| f[bar_index]).collect(),
+        })
+        .collect();
+    Ok(explanations)
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use crate::lorentzian_classification::types::DEFAULT_FEATURES;
+
+    fn settings() -> Settings<'static> {
+        Settings {
+            source: "close",
+            neighbors_count: 8,
+            max_bars_back: 2000,
+            feature_count: 5,
+            features: DEFAULT_FEATURES,
+            show_exits: false,
+            use_dynamic_exits: false,
+            use_ema_filter: false,
+            ema_period: 200,
+            use_sma_filter: false,
+            sma_period: 200,
+        }
+    }
+
+    fn filters() -> Filters {
+        Filters {
+            use_volatility_filter: true,
+            use_regime_filter: true,
+            use_adx_filter: false,
+            regime_threshold: -0.1,
+            adx_threshold: 20,
+        }
+    }
+
+    fn kernel_filter() -> KernelFilter {
+        KernelFilter {
+            use_kernel_filter: true,
+            show_kernel_estimate: true,
+            use_kernel_smoothing: false,
+            look_back_window: 8,
+            relative_weight: 8.0,
+            regression_level: 25.0,
+            crossover_lag: 2,
+            adaptive_bandwidth: None,
+        }
+    }
+
+    #[test]
+    fn test_lorentzian_classification() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let result = lorentzian_classification(&df, &settings(), &filters(), &kernel_filter())?;
+        for name in ["prediction", "signal", "start_long", "start_short", "end_long", "end_short", "kernel_estimate"] {
+            assert_eq!(result.column(name)?.len(), df.height());
+        }
+        // predictions are sums of at most `neighbors_count` labels
+        let prediction = result.column("prediction")?.f64()?.clone();
+        assert!(prediction.into_no_null_iter().all(|p| p.abs() <= 8.0));
+        // a trade is never opened in both directions on the same bar
+        let start_long = result.column("start_long")?.bool()?.clone();
+        let start_short = result.column("start_short")?.bool()?.clone();
+        assert!(start_long.into_no_null_iter().zip(start_short.into_no_null_iter()).all(|(l, s)| !(l && s)));
+        eprintln!("{:?}", result.select(["close", "prediction", "signal", "start_long", "start_short", "end_long", "end_short"])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dynamic_exits() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let settings = Settings { use_dynamic_exits: true, ..settings() };
+        let result = lorentzian_classification(&df, &settings, &filters(), &kernel_filter())?;
+        let end_long = result.column("end_long")?.bool()?.clone();
+        assert!(end_long.into_no_null_iter().any(|e| e));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dynamic_exits_without_kernel_filter() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::lorentzian_classification::signals::kernel_signals;
+        let df = example()?;
+        let settings = Settings { use_dynamic_exits: true, ..settings() };
+        let kernel = KernelFilter { use_kernel_filter: false, ..kernel_filter() };
+        let result = lorentzian_classification(&df, &settings, &filters(), &kernel)?;
+        let flags = |df: &DataFrame, name: &str| -> Vec<bool> { df.column(name).unwrap().bool().unwrap().into_no_null_iter().collect() };
+        let (start_long, start_short) = (flags(&result, "start_long"), flags(&result, "start_short"));
+        let signals = kernel_signals(df.column("close")?, &kernel)?;
+        let (bullish_change, bearish_change) = (flags(&signals, "is_bullish_change"), flags(&signals, "is_bearish_change"));
+
+        // pine: endLongTradeDynamic = isBearishChange and isValidLongExit[1],
+        // isValidLongExit = barssince(alertBearish) > barssince(startLongTrade)
+        let last = |flags: &[bool], i: usize| (0..=i).rev().find(|&j| flags[j]);
+        let end = |change: &[bool], against: &[bool], start: &[bool]| -> Vec<bool> {
+            (0..change.len()).map(|i| i >= 1 && change[i] && matches!((last(against, i - 1), last(start, i - 1)), (Some(x), Some(e)) if x < e)).collect()
+        };
+        let end_long = end(&bearish_change, &bearish_change, &start_long);
+        let end_short = end(&bullish_change, &bullish_change, &start_short);
+        assert_eq!(flags(&result, "end_long"), end_long);
+        assert_eq!(flags(&result, "end_short"), end_short);
+        assert!(end_long.iter().any(|e| *e) && end_short.iter().any(|e| *e));
+        // some exits close a trade that was already reversed, pine still plots them
+        let last_start = |i: usize| (0..=i).rev().find(|&j| start_long[j] || start_short[j]).map(|j| start_long[j]);
+        assert!((1..end_long.len()).any(|i| end_long[i] && last_start(i - 1) == Some(false)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_labeler() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::lorentzian_classification::labels::TripleBarrierLabeler;
+        let df = example()?;
+        let labeler = TripleBarrierLabeler { profit_target: 0.005, stop_loss: 0.005, time_limit: 8 };
+        let result = lorentzian_classification_with_labeler(&df, &settings(), &filters(), &kernel_filter(), &labeler)?;
+        let default = lorentzian_classification(&df, &settings(), &filters(), &kernel_filter())?;
+        assert!(!result.column("prediction")?.series_equal(default.column("prediction")?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_classification() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let explanations = explain_classification(&df, &settings(), &FixedHorizonLabeler::default())?;
+        let result = lorentzian_classification(&df, &settings(), &filters(), &kernel_filter())?;
+        let prediction: Vec<f64> = result.column("prediction")?.f64()?.into_no_null_iter().collect();
+        assert_eq!(explanations.len(), df.height());
+        for (explanation, p) in explanations.iter().zip(prediction) {
+            assert_eq!(explanation.prediction, p);
+            assert!((-1.0..=1.0).contains(&explanation.confidence));
+            assert_eq!(explanation.prediction, explanation.neighbor_labels.iter().sum::<i32>() as f64);
+            assert_eq!(explanation.neighbor_indices.len(), explanation.neighbor_distances.len());
+            assert_eq!(explanation.features.len(), 5);
+        }
+        let last = explanations.last().unwrap();
+        assert_eq!(last.confidence, last.prediction / 8.0);
+        Ok(())
+    }
 }
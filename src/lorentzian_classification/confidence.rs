@@ -0,0 +1,168 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/27 09:50
+ * @Email: uyplayer@qq.com
+ * @File: confidence
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! standard errors and confidence bands of the kernel estimates
+
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::regression::{kernel_convolve, kernel_values, kernel_weights, Kernel, NanPolicy};
+
+
+/// Calculates the kernel estimate with its standard error and confidence bands.
+///
+/// The estimate is the same as [`crate::kernel_regression`], so `rational_quadratic` and `gaussian` are
+/// reproduced with a [`crate::RationalQuadraticKernel`] and a [`crate::GaussianKernel`]. In the window of a bar,
+/// the residual variance is the weighted mean of the squared residuals `sum(w * (y - yhat)^2) / sum(w)` and
+/// the variance of the estimate is `residual variance * sum(w^2) / sum(w)^2`. The bands are
+/// `yhat +- z * std_error` with `z` the normal quantile of the two-sided `level`.
+/// Missing values are skipped as in the kernel functions, every column is null during the warm up.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `kernel` - The kernel weighting the values by their distance.
+/// * `start_at_bar` - The starting point for the calculation.
+/// * `level` - The confidence level of the bands, e.g. `0.95`.
+///
+/// # Returns
+///
+/// A DataFrame with the columns
+/// * `estimate` - the kernel estimate
+/// * `residual_variance` - the weighted variance of the values around the estimate
+/// * `variance`, `std_error` - the variance and the standard error of the estimate
+/// * `upper`, `lower` - the confidence bands
+/// * `above_upper`, `below_lower` - the value is outside the bands, e.g. for a band exit rule
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{kernel_confidence, GaussianKernel};
+/// let src = Series::new("data", (0..40).map(|i| (i as f64 / 3.0).sin()).collect::<Vec<f64>>());
+/// let bands = kernel_confidence(&src, &GaussianKernel { look_back: 8.0 }, 25, 0.95).unwrap();
+/// println!("{:?}", bands);
+/// ```
+pub fn kernel_confidence<K: Kernel + ?Sized>(src: &Series, kernel: &K, start_at_bar: i32, level: f64) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    if start_at_bar < 0 {
+        return Err("start_at_bar must not be negative".into());
+    }
+    if !(level > 0.0 && level < 1.0) {
+        return Err(format!("level must be between 0 and 1, got {}", level).into());
+    }
+    let weights = kernel_weights(kernel, (start_at_bar + 2) as usize);
+    let values = kernel_values(src, NanPolicy::default())?;
+    let estimate = kernel_convolve(&values, &weights)?;
+    let z = helper::normal_quantile(0.5 + level / 2.0);
+    let size = weights.len();
+    let n = values.len();
+
+    let mut residual_variance = vec![None; n];
+    let mut variance = vec![None; n];
+    for bar_index in size - 1..n {
+        let Some(yhat) = estimate[bar_index] else { continue };
+        let (mut squared_residuals, mut cumulative_weight, mut squared_weights) = (0.0, 0.0, 0.0);
+        for (y, w) in values[bar_index + 1 - size..=bar_index].iter().rev().zip(&weights) {
+            if let Some(y) = y {
+                squared_residuals += w * (y - yhat).powi(2);
+                cumulative_weight += w;
+                squared_weights += w * w;
+            }
+        }
+        let sigma_squared = squared_residuals / cumulative_weight;
+        residual_variance[bar_index] = Some(sigma_squared);
+        variance[bar_index] = Some(sigma_squared * squared_weights / cumulative_weight.powi(2));
+    }
+    let std_error: Vec<Option<f64>> = variance.iter().map(|v| v.map(f64::sqrt)).collect();
+    let upper: Vec<Option<f64>> = estimate.iter().zip(&std_error).map(|(e, s)| e.zip(*s).map(|(e, s)| e + z * s)).collect();
+    let lower: Vec<Option<f64>> = estimate.iter().zip(&std_error).map(|(e, s)| e.zip(*s).map(|(e, s)| e - z * s)).collect();
+    let above_upper: Vec<bool> = values.iter().zip(&upper).map(|(y, u)| matches!((y, u), (Some(y), Some(u)) if y > u)).collect();
+    let below_lower: Vec<bool> = values.iter().zip(&lower).map(|(y, l)| matches!((y, l), (Some(y), Some(l)) if y < l)).collect();
+    Ok(DataFrame::new(vec![
+        Series::new("estimate", estimate),
+        Series::new("residual_variance", residual_variance),
+        Series::new("variance", variance),
+        Series::new("std_error", std_error),
+        Series::new("upper", upper),
+        Series::new("lower", lower),
+        Series::new("above_upper", above_upper),
+        Series::new("below_lower", below_lower),
+    ])?)
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use crate::lorentzian_classification::kernel::{gaussian, rational_quadratic};
+    use crate::lorentzian_classification::regression::{GaussianKernel, RationalQuadraticKernel};
+
+    fn column(df: &DataFrame, name: &str) -> Vec<Option<f64>> {
+        df.column(name).unwrap().f64().unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn test_kernel_confidence() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        let bands = kernel_confidence(close, &RationalQuadraticKernel { look_back: 8.0, relative_weight: 8.0 }, 25, 0.95)?;
+        let mut expected = rational_quadratic(close, 8, 8.0, 25)?;
+        assert!(bands.column("estimate")?.series_equal_missing(expected.rename("estimate")));
+
+        let bands = kernel_confidence(close, &GaussianKernel { look_back: 8.0 }, 25, 0.95)?;
+        let (estimate, variance, std_error) = (column(&bands, "estimate"), column(&bands, "variance"), column(&bands, "std_error"));
+        let (upper, lower) = (column(&bands, "upper"), column(&bands, "lower"));
+        assert_eq!(estimate, gaussian(close, 8, 25)?.f64()?.into_iter().collect::<Vec<Option<f64>>>());
+        assert_eq!(bands.column("upper")?.null_count(), 26);
+        for t in 26..estimate.len() {
+            let (e, s) = (estimate[t].unwrap(), std_error[t].unwrap());
+            assert_eq!(s, variance[t].unwrap().sqrt());
+            assert!((upper[t].unwrap() - (e + 1.959963985 * s)).abs() <= 1e-6 * s.max(1e-12));
+            assert!((e - lower[t].unwrap() - (upper[t].unwrap() - e)).abs() <= 1e-9 * e);
+        }
+
+        // the variance of a bar by hand
+        let values: Vec<f64> = close.f64()?.into_no_null_iter().collect();
+        let weights = kernel_weights(&GaussianKernel { look_back: 8.0 }, 27);
+        let t = 100;
+        let yhat = estimate[t].unwrap();
+        let window: Vec<(f64, f64)> = (0..27).map(|i| (values[t - i], weights[i])).collect();
+        let total: f64 = weights.iter().sum();
+        let sigma_squared = window.iter().map(|(y, w)| w * (y - yhat).powi(2)).sum::<f64>() / total;
+        let expected = sigma_squared * weights.iter().map(|w| w * w).sum::<f64>() / total.powi(2);
+        assert!((variance[t].unwrap() - expected).abs() <= 1e-9 * expected);
+        assert!((column(&bands, "residual_variance")[t].unwrap() - sigma_squared).abs() <= 1e-9 * sigma_squared);
+
+        // a higher level widens the bands
+        let wide = kernel_confidence(close, &GaussianKernel { look_back: 8.0 }, 25, 0.99)?;
+        let wide_upper = column(&wide, "upper");
+        assert!((26..estimate.len()).all(|t| wide_upper[t] >= upper[t]));
+        // the values outside the bands
+        let above: Vec<bool> = bands.column("above_upper")?.bool()?.into_no_null_iter().collect();
+        assert!((0..values.len()).all(|t| above[t] == upper[t].is_some_and(|u| values[t] > u)));
+        assert!(above.iter().any(|a| *a));
+        Ok(())
+    }
+
+    #[test]
+    fn test_constant_series() -> Result<(), Box<dyn std::error::Error>> {
+        // no residuals, no uncertainty
+        let src = Series::new("data", vec![Some(2.0), None, Some(2.0), Some(2.0), Some(2.0)]);
+        let bands = kernel_confidence(&src, &GaussianKernel { look_back: 2.0 }, 1, 0.9)?;
+        assert_eq!(column(&bands, "std_error"), vec![None, None, Some(0.0), Some(0.0), Some(0.0)]);
+        assert_eq!(column(&bands, "upper"), column(&bands, "lower"));
+        assert!(kernel_confidence(&src, &GaussianKernel { look_back: 2.0 }, 1, 1.0).is_err());
+        assert!(kernel_confidence(&src, &GaussianKernel { look_back: 2.0 }, 1, 0.0).is_err());
+        Ok(())
+    }
+}
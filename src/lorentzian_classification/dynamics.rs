@@ -0,0 +1,201 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/25 15:40
+ * @Email: uyplayer@qq.com
+ * @File: dynamics
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! slope, curvature and turning points of the kernel line
+
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::regression::{kernel_regression, Kernel};
+
+
+/// Whether a turning point is a local peak or a local trough of the kernel line.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TurningPointKind {
+    /// The line rose into the bar and fell after it.
+    Peak,
+    /// The line fell into the bar and rose after it.
+    Trough,
+}
+
+/// A confirmed turning point of the kernel line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurningPoint {
+    /// The bar of the peak or the trough.
+    pub bar_index: usize,
+    /// The bar the turning point is known at, `bar_index + confirmation_lag`.
+    pub confirmed_at: usize,
+    /// Peak or trough.
+    pub kind: TurningPointKind,
+    /// The kernel estimate at `bar_index`.
+    pub value: f64,
+}
+
+/// Differences of consecutive values, `None` where either value is missing.
+fn differences(values: &[Option<f64>]) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+    for i in 1..values.len() {
+        result[i] = values[i].zip(values[i - 1]).map(|(current, previous)| current - previous);
+    }
+    result
+}
+
+/// Calculates the kernel estimate with its first and second differences.
+///
+/// The estimate is the same as [`kernel_regression`], so `rational_quadratic` and `gaussian` are
+/// reproduced with a [`crate::RationalQuadraticKernel`] and a [`crate::GaussianKernel`].
+/// The slope of a bar is `yhat[t] - yhat[t-1]`, the curvature `slope[t] - slope[t-1]`,
+/// both are null where a value they need is null.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `kernel` - The kernel weighting the values by their distance.
+/// * `start_at_bar` - The starting point for the calculation.
+///
+/// # Returns
+///
+/// A DataFrame with the `estimate`, `slope` and `curvature` columns.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{kernel_dynamics, GaussianKernel};
+/// let src = Series::new("data", vec![1.0, 2.0, 4.0, 7.0, 11.0, 16.0]);
+/// let result = kernel_dynamics(&src, &GaussianKernel { look_back: 2.0 }, 1).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn kernel_dynamics<K: Kernel + ?Sized>(src: &Series, kernel: &K, start_at_bar: i32) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let mut estimate = kernel_regression(src, kernel, start_at_bar)?;
+    estimate.rename("estimate");
+    let values = helper::series_options(&estimate)?;
+    let slope = differences(&values);
+    let curvature = differences(&slope);
+    Ok(DataFrame::new(vec![
+        estimate,
+        Series::new("slope", slope),
+        Series::new("curvature", curvature),
+    ])?)
+}
+
+/// Finds the peaks and troughs of a kernel line.
+///
+/// A peak is a bar above the bar before it, followed by `confirmation_lag` strictly falling bars,
+/// a trough the opposite. The turning point is only known at `bar_index + confirmation_lag`,
+/// so acting on it at `confirmed_at` never uses future bars. A lag of 1 is the change of direction
+/// of the tradingview script. Bars with a missing value never take part in a turning point.
+///
+/// # Arguments
+///
+/// * `estimate` - The kernel line, e.g. the output of `rational_quadratic` or `gaussian`.
+/// * `confirmation_lag` - The number of bars confirming the new direction, at least 1.
+///
+/// # Returns
+///
+/// The turning points in the order of the bars.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{turning_points, TurningPointKind};
+/// let estimate = Series::new("estimate", vec![1.0, 2.0, 3.0, 2.5, 2.0, 1.5]);
+/// let points = turning_points(&estimate, 2).unwrap();
+/// assert_eq!(points[0].bar_index, 2);
+/// assert_eq!(points[0].confirmed_at, 4);
+/// assert_eq!(points[0].kind, TurningPointKind::Peak);
+/// ```
+pub fn turning_points(estimate: &Series, confirmation_lag: usize) -> Result<Vec<TurningPoint>, Box<dyn std::error::Error>> {
+    if confirmation_lag == 0 {
+        return Err("confirmation_lag must be bigger than zero".into());
+    }
+    let values = helper::series_options(estimate)?;
+    let slope = differences(&values);
+    let mut points = Vec::new();
+    for bar_index in 1..slope.len().saturating_sub(confirmation_lag) {
+        let Some(before) = slope[bar_index] else { continue };
+        let after = &slope[bar_index + 1..=bar_index + confirmation_lag];
+        let kind = if before > 0.0 && after.iter().all(|s| s.is_some_and(|s| s < 0.0)) {
+            TurningPointKind::Peak
+        } else if before < 0.0 && after.iter().all(|s| s.is_some_and(|s| s > 0.0)) {
+            TurningPointKind::Trough
+        } else {
+            continue;
+        };
+        points.push(TurningPoint {
+            bar_index,
+            confirmed_at: bar_index + confirmation_lag,
+            kind,
+            value: values[bar_index].unwrap(),
+        });
+    }
+    Ok(points)
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use crate::lorentzian_classification::kernel::{gaussian, rational_quadratic};
+    use crate::lorentzian_classification::regression::{GaussianKernel, RationalQuadraticKernel};
+
+    #[test]
+    fn test_kernel_dynamics() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        let dynamics = kernel_dynamics(close, &RationalQuadraticKernel { look_back: 8.0, relative_weight: 8.0 }, 25)?;
+        let mut estimate = rational_quadratic(close, 8, 8.0, 25)?;
+        assert!(dynamics.column("estimate")?.series_equal_missing(estimate.rename("estimate")));
+        let dynamics = kernel_dynamics(close, &GaussianKernel { look_back: 6.0 }, 25)?;
+        let estimate: Vec<Option<f64>> = gaussian(close, 6, 25)?.f64()?.into_iter().collect();
+        let slope: Vec<Option<f64>> = dynamics.column("slope")?.f64()?.into_iter().collect();
+        let curvature: Vec<Option<f64>> = dynamics.column("curvature")?.f64()?.into_iter().collect();
+        // one more null bar for every difference
+        assert_eq!(slope.iter().filter(|s| s.is_none()).count(), 27);
+        assert_eq!(curvature.iter().filter(|c| c.is_none()).count(), 28);
+        for t in 28..estimate.len() {
+            assert_eq!(slope[t], Some(estimate[t].unwrap() - estimate[t - 1].unwrap()));
+            assert_eq!(curvature[t], Some(slope[t].unwrap() - slope[t - 1].unwrap()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_turning_points() -> Result<(), Box<dyn std::error::Error>> {
+        let estimate = Series::new("estimate", vec![None, Some(1.0), Some(2.0), Some(3.0), Some(2.0), Some(1.0), Some(2.0), Some(1.0), Some(0.0), Some(1.0)]);
+        let points = turning_points(&estimate, 1)?;
+        let bars: Vec<(usize, TurningPointKind)> = points.iter().map(|p| (p.bar_index, p.kind)).collect();
+        assert_eq!(bars, vec![(3, TurningPointKind::Peak), (5, TurningPointKind::Trough), (6, TurningPointKind::Peak), (8, TurningPointKind::Trough)]);
+        assert_eq!(points[0].value, 3.0);
+        // a longer lag drops the short swings
+        let points = turning_points(&estimate, 2)?;
+        let bars: Vec<(usize, usize)> = points.iter().map(|p| (p.bar_index, p.confirmed_at)).collect();
+        assert_eq!(bars, vec![(3, 5), (6, 8)]);
+        assert!(turning_points(&estimate, 0).is_err());
+
+        // the turning points of a kernel are its changes of direction
+        let df = example()?;
+        let estimate = gaussian(df.column("close")?, 6, 25)?;
+        let values: Vec<Option<f64>> = estimate.f64()?.into_iter().collect();
+        for point in turning_points(&estimate, 3)? {
+            let t = point.bar_index;
+            let (before, after) = (values[t - 1].unwrap(), values[t + 3].unwrap());
+            match point.kind {
+                TurningPointKind::Peak => assert!(before < point.value && after < point.value),
+                TurningPointKind::Trough => assert!(before > point.value && after > point.value),
+            }
+        }
+        Ok(())
+    }
+}
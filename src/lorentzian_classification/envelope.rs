@@ -0,0 +1,234 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/22 10:20
+ * @Email: uyplayer@qq.com
+ * @File: envelope
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! nadaraya-watson envelope, port of the tradingview script by jdehorty
+
+use polars::prelude::*;
+use crate::lorentzian_classification::{helper, kernel};
+use crate::lorentzian_classification::types::ConfigCheck;
+
+
+/// The settings of the Nadaraya-Watson envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope<'a> {
+    /// The source column of the mid-line, usually close.
+    pub source: &'a str,
+    /// The look back window of the rational quadratic kernel.
+    pub look_back: i32,
+    /// The relative weight of the rational quadratic kernel.
+    pub relative_weight: f32,
+    /// The starting point of the kernel regression.
+    pub start_at_bar: i32,
+    /// The length of the ATR of the kernel estimates.
+    pub atr_length: usize,
+    /// The ATR multiplier of the near bands.
+    pub near_factor: f64,
+    /// The ATR multiplier of the far bands.
+    pub far_factor: f64,
+}
+
+impl Default for Envelope<'_> {
+    /// The default inputs of the tradingview script.
+    fn default() -> Self {
+        Envelope {
+            source: "close",
+            look_back: 8,
+            relative_weight: 8.0,
+            start_at_bar: 25,
+            atr_length: 60,
+            near_factor: 1.5,
+            far_factor: 8.0,
+        }
+    }
+}
+
+impl ConfigCheck for Envelope<'_> {
+    type Output = ();
+    fn configuration_check(&self) -> Self::Output {
+        if self.look_back < 1 {
+            panic!("look_back must be bigger than zero");
+        }
+        if self.relative_weight <= 0.0 {
+            panic!("relative_weight must be bigger than zero");
+        }
+        if self.start_at_bar < 0 {
+            panic!("start_at_bar must not be negative");
+        }
+        if self.atr_length == 0 {
+            panic!("atr_length must be bigger than zero");
+        }
+        if self.near_factor <= 0.0 || self.far_factor < self.near_factor {
+            panic!("near_factor must be bigger than zero and not bigger than far_factor");
+        }
+    }
+}
+
+/// Calculates the Nadaraya-Watson envelope of an OHLC DataFrame.
+///
+/// The mid-line is the `rational_quadratic` estimate of the source, the bands are the mid-line
+/// plus and minus the ATR of the kernel estimates of high, low and close, multiplied by
+/// `near_factor` and `far_factor`. The values are null until the kernel and the ATR are warmed up.
+/// A bar where a kernel estimate is missing, e.g. after a gap longer than the window, is skipped by
+/// the ATR: its bands are null and the ATR continues from the last bar with every estimate.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame with the `high`, `low`, `close` and source columns.
+/// * `envelope` - The settings of the envelope.
+///
+/// # Returns
+///
+/// A DataFrame with the columns
+/// * `yhat` - the kernel mid-line, null during the warm up
+/// * `upper_near`, `upper_far`, `upper_avg` - the upper bands and their average
+/// * `lower_near`, `lower_far`, `lower_avg` - the lower bands and their average
+/// * `touch_upper_near`, `touch_upper_far` - the high reached the upper band
+/// * `touch_lower_near`, `touch_lower_far` - the low reached the lower band
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{nadaraya_watson_envelope, Envelope};
+/// let close: Vec<f64> = (0..200).map(|i| 100.0 + (i as f64 / 5.0).sin() * 10.0).collect();
+/// let df = df!(
+///     "high" => close.iter().map(|c| c + 1.0).collect::<Vec<f64>>(),
+///     "low" => close.iter().map(|c| c - 1.0).collect::<Vec<f64>>(),
+///     "close" => close.clone()
+/// ).unwrap();
+/// let result = nadaraya_watson_envelope(&df, &Envelope::default()).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn nadaraya_watson_envelope(df: &DataFrame, envelope: &Envelope) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    envelope.configuration_check();
+    let estimate = |name: &str| -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+        let line = kernel::rational_quadratic(df.column(name)?, envelope.look_back, envelope.relative_weight, envelope.start_at_bar)?;
+        helper::series_values(&line)
+    };
+    let yhat_high = estimate("high")?;
+    let yhat_low = estimate("low")?;
+    let yhat_close = estimate("close")?;
+    let yhat = estimate(envelope.source)?;
+    let high = helper::column_values(df, "high")?;
+    let low = helper::column_values(df, "low")?;
+
+    // the kernel estimates are null, so NaN, during the warm up and without values in the window,
+    // the ATR runs over the bars with every estimate so a missing one does not poison its average
+    let present: Vec<usize> = (0..yhat.len())
+        .filter(|&i| !(yhat_high[i].is_nan() || yhat_low[i].is_nan() || yhat_close[i].is_nan()))
+        .collect();
+    let pick = |values: &[f64]| -> Vec<f64> { present.iter().map(|&i| values[i]).collect() };
+    let mut atr = vec![f64::NAN; yhat.len()];
+    for (&i, value) in present.iter().zip(helper::atr(&pick(&yhat_high), &pick(&yhat_low), &pick(&yhat_close), envelope.atr_length)) {
+        atr[i] = value;
+    }
+
+    let band = |factor: f64| -> Vec<f64> { yhat.iter().zip(&atr).map(|(y, a)| y + factor * a).collect() };
+    let upper_near = band(envelope.near_factor);
+    let upper_far = band(envelope.far_factor);
+    let lower_near = band(-envelope.near_factor);
+    let lower_far = band(-envelope.far_factor);
+    let average = |a: &[f64], b: &[f64]| -> Vec<f64> { a.iter().zip(b).map(|(a, b)| (a + b) / 2.0).collect() };
+    let upper_avg = average(&upper_near, &upper_far);
+    let lower_avg = average(&lower_near, &lower_far);
+
+    // comparisons with NaN are false, nothing is touched during the warm up or a gap
+    let touch_upper_near: Vec<bool> = high.iter().zip(&upper_near).map(|(h, u)| h >= u).collect();
+    let touch_upper_far: Vec<bool> = high.iter().zip(&upper_far).map(|(h, u)| h >= u).collect();
+    let touch_lower_near: Vec<bool> = low.iter().zip(&lower_near).map(|(l, b)| l <= b).collect();
+    let touch_lower_far: Vec<bool> = low.iter().zip(&lower_far).map(|(l, b)| l <= b).collect();
+
+    Ok(DataFrame::new(vec![
+        Series::new("yhat", helper::nan_to_null(&yhat)),
+        Series::new("upper_near", helper::nan_to_null(&upper_near)),
+        Series::new("upper_far", helper::nan_to_null(&upper_far)),
+        Series::new("upper_avg", helper::nan_to_null(&upper_avg)),
+        Series::new("lower_near", helper::nan_to_null(&lower_near)),
+        Series::new("lower_far", helper::nan_to_null(&lower_far)),
+        Series::new("lower_avg", helper::nan_to_null(&lower_avg)),
+        Series::new("touch_upper_near", touch_upper_near),
+        Series::new("touch_upper_far", touch_upper_far),
+        Series::new("touch_lower_near", touch_lower_near),
+        Series::new("touch_lower_far", touch_lower_far),
+    ])?)
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+
+    fn values(df: &DataFrame, name: &str) -> Vec<Option<f64>> {
+        df.column(name).unwrap().f64().unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn test_envelope() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let envelope = Envelope::default();
+        let result = nadaraya_watson_envelope(&df, &envelope)?;
+        assert_eq!(result.height(), df.height());
+        let yhat = values(&result, "yhat");
+        let upper_near = values(&result, "upper_near");
+        let upper_far = values(&result, "upper_far");
+        let lower_near = values(&result, "lower_near");
+        let lower_far = values(&result, "lower_far");
+        // warm up of the kernel and of the ATR
+        let warm_up = 26 + 60 - 1;
+        assert!(upper_near[..warm_up].iter().all(|v| v.is_none()));
+        assert!(yhat[..26].iter().all(|v| v.is_none()) && yhat[26].is_some());
+        for i in warm_up..df.height() {
+            let (yhat, upper_near, upper_far) = (yhat[i].unwrap(), upper_near[i].unwrap(), upper_far[i].unwrap());
+            let (lower_near, lower_far) = (lower_near[i].unwrap(), lower_far[i].unwrap());
+            assert!(lower_far < lower_near && lower_near < yhat);
+            assert!(yhat < upper_near && upper_near < upper_far);
+        }
+        // the mid-line is the kernel estimate, nulls included
+        let yhat_close = kernel::rational_quadratic(df.column("close")?, 8, 8.0, 25)?;
+        assert!(result.column("yhat")?.series_equal_missing(yhat_close.clone().rename("yhat")));
+
+        // the far bands are touched less often than the near bands
+        let touches = |name: &str| result.column(name).unwrap().bool().unwrap().into_no_null_iter().filter(|t| *t).count();
+        assert!(touches("touch_upper_near") > 0 && touches("touch_upper_near") >= touches("touch_upper_far"));
+        assert!(touches("touch_lower_near") > 0 && touches("touch_lower_near") >= touches("touch_lower_far"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_envelope_with_gap() -> Result<(), Box<dyn std::error::Error>> {
+        // a gap longer than the kernel window leaves the estimates of high missing
+        let mut df = example()?;
+        let high: Vec<Option<f64>> = helper::column_values(&df, "high")?
+            .into_iter()
+            .enumerate()
+            .map(|(i, h)| if (1000..1040).contains(&i) { None } else { Some(h) })
+            .collect();
+        df.with_column(Series::new("high", high))?;
+        let result = nadaraya_watson_envelope(&df, &Envelope::default())?;
+        let upper_near = values(&result, "upper_near");
+        let missing: Vec<usize> = (0..df.height()).filter(|&i| i >= 100 && upper_near[i].is_none()).collect();
+        assert!(!missing.is_empty() && missing.iter().all(|i| (1000..1040 + 27).contains(i)));
+        // the bands come back after the gap
+        assert!(upper_near[1040 + 27..].iter().all(|v| v.is_some()));
+        let touches = result.column("touch_upper_near")?.bool()?.clone();
+        assert!(missing.iter().all(|&i| touches.get(i) == Some(false)));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_envelope_configuration() {
+        Envelope { near_factor: 2.0, far_factor: 1.0, ..Envelope::default() }.configuration_check();
+    }
+}
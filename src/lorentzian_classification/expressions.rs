@@ -0,0 +1,145 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/24 16:05
+ * @Email: uyplayer@qq.com
+ * @File: expressions
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! `ta()` expression namespace, the kernels and helpers inside polars lazy queries
+
+use polars::prelude::*;
+use crate::lorentzian_classification::{helper, kernel};
+
+
+/// Adds the `ta()` namespace to polars expressions.
+///
+/// The functions run on every group of a `group_by` aggregation or an `over` window,
+/// so an indicator is computed per symbol of a multi-asset frame in one query.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::TaExpr;
+/// let df = df!(
+///     "symbol" => ["BTC", "BTC", "BTC", "BTC", "ETH", "ETH", "ETH", "ETH"],
+///     "close" => [1.0, 2.0, 3.0, 4.0, 10.0, 20.0, 30.0, 40.0]
+/// ).unwrap();
+/// let result = df
+///     .lazy()
+///     .with_column(col("close").ta().rational_quadratic(2, 3.0, 1).over([col("symbol")]).alias("kernel"))
+///     .collect()
+///     .unwrap();
+/// println!("{:?}", result);
+/// ```
+pub trait TaExpr {
+    /// The technical analysis namespace of the expression.
+    fn ta(self) -> TaNameSpace;
+}
+
+impl TaExpr for Expr {
+    fn ta(self) -> TaNameSpace {
+        TaNameSpace(self)
+    }
+}
+
+/// The technical analysis functions of an expression, see [`TaExpr`].
+pub struct TaNameSpace(Expr);
+
+/// Applies a series function to every group of the expression.
+fn apply_series<F>(expr: Expr, function: F) -> Expr
+where
+    F: Fn(&Series) -> Result<Series, Box<dyn std::error::Error>> + 'static + Send + Sync,
+{
+    expr.apply(
+        move |s| function(&s).map(Some).map_err(|e| PolarsError::ComputeError(e.to_string().into())),
+        GetOutput::from_type(DataType::Float64),
+    )
+}
+
+impl TaNameSpace {
+    /// The `rational_quadratic` kernel estimate, see [`crate::rational_quadratic`].
+    pub fn rational_quadratic(self, look_back: i32, relative_weight: f32, start_at_bar: i32) -> Expr {
+        apply_series(self.0, move |s| kernel::rational_quadratic(s, look_back, relative_weight, start_at_bar))
+    }
+
+    /// The `gaussian` kernel estimate, see [`crate::gaussian`].
+    pub fn gaussian(self, look_back: i32, start_at_bar: i32) -> Expr {
+        apply_series(self.0, move |s| kernel::gaussian(s, look_back, start_at_bar))
+    }
+
+    /// The values normalized to `min_val..=max_val`, see [`crate::normalizer`].
+    pub fn normalize(self, min_val: f64, max_val: f64) -> Expr {
+        apply_series(self.0, move |s| helper::normalizer(s, min_val, max_val))
+    }
+
+    /// The rolling moving average, see [`crate::rma_indicator`].
+    pub fn rma(self, length: i32) -> Expr {
+        apply_series(self.0, move |s| helper::rma_indicator(s, length))
+    }
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> PolarsResult<DataFrame> {
+        let btc: Vec<f64> = (0..40).map(|i| 100.0 + (i as f64 / 3.0).sin() * 10.0).collect();
+        let eth: Vec<f64> = (0..40).map(|i| 10.0 + (i as f64 / 5.0).cos()).collect();
+        df!(
+            "symbol" => [vec!["BTC"; 40], vec!["ETH"; 40]].concat(),
+            "close" => [btc, eth].concat()
+        )
+    }
+
+    #[test]
+    fn test_over_symbol() -> Result<(), Box<dyn std::error::Error>> {
+        // a symbol listed later, its first close is missing
+        let sol: Vec<Option<f64>> = (0..40).map(|i| if i == 0 { None } else { Some(20.0 + (i as f64 / 4.0).sin()) }).collect();
+        let df = frame()?.vstack(&df!("symbol" => vec!["SOL"; 40], "close" => sol)?)?;
+        let result = df
+            .clone()
+            .lazy()
+            .with_columns([
+                col("close").ta().rational_quadratic(8, 8.0, 25).over([col("symbol")]).alias("rational_quadratic"),
+                col("close").ta().gaussian(6, 25).over([col("symbol")]).alias("gaussian"),
+                col("close").ta().normalize(0.0, 1.0).over([col("symbol")]).alias("normalize"),
+                col("close").ta().rma(10).over([col("symbol")]).alias("rma"),
+            ])
+            .collect()?;
+        for (symbol, offset) in [("BTC", 0), ("ETH", 40), ("SOL", 80)] {
+            let close = df.column("close")?.slice(offset, 40);
+            // the output columns carry the alias, the direct results the input name
+            let same = |name: &str, expected: Series| -> PolarsResult<bool> {
+                Ok(result.column(name)?.slice(offset, 40).series_equal_missing(expected.clone().rename(name)))
+            };
+            assert!(same("rational_quadratic", kernel::rational_quadratic(&close, 8, 8.0, 25)?)?, "{}", symbol);
+            assert!(same("gaussian", kernel::gaussian(&close, 6, 25)?)?, "{}", symbol);
+            assert!(same("normalize", helper::normalizer(&close, 0.0, 1.0)?)?, "{}", symbol);
+            assert!(same("rma", helper::rma_indicator(&close, 10)?)?, "{}", symbol);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by() -> Result<(), Box<dyn std::error::Error>> {
+        let result = frame()?
+            .lazy()
+            .groupby_stable([col("symbol")])
+            .agg([col("close").ta().gaussian(6, 25).alias("gaussian")])
+            .explode([col("gaussian")])
+            .collect()?;
+        assert_eq!(result.height(), 80);
+        // every symbol has its own warm up
+        assert_eq!(result.column("gaussian")?.null_count(), 52);
+        assert!(frame()?.lazy().select([col("symbol").ta().gaussian(6, 25)]).collect().is_err());
+        Ok(())
+    }
+}
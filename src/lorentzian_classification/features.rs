@@ -0,0 +1,173 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/9 10:21
+ * @Email: uyplayer@qq.com
+ * @File: features
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! feature engineering for lorentzian classification
+
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::types::{ConfigCheck, FeatureSpec, Settings};
+
+
+/// Calculates the values of a feature from an OHLC DataFrame.
+pub(crate) fn feature_values(df: &DataFrame, feature: &FeatureSpec) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let close = helper::column_values(df, "close")?;
+    let values = match *feature {
+        FeatureSpec::RSI { param_a, param_b } => helper::n_rsi(&close, param_a as usize, param_b as usize)?,
+        FeatureSpec::CCI { param_a, param_b } => helper::n_cci(&close, param_a as usize, param_b as usize),
+        FeatureSpec::WT { param_a, param_b } => {
+            let high = helper::column_values(df, "high")?;
+            let low = helper::column_values(df, "low")?;
+            let hlc3: Vec<f64> = (0..close.len()).map(|i| (high[i] + low[i] + close[i]) / 3.0).collect();
+            helper::n_wt(&hlc3, param_a as usize, param_b as usize)
+        }
+        FeatureSpec::ADX { param_a } => {
+            let high = helper::column_values(df, "high")?;
+            let low = helper::column_values(df, "low")?;
+            helper::n_adx(&high, &low, &close, param_a as usize)?
+        }
+    };
+    Ok(values)
+}
+
+/// Calculates a normalized feature series from an OHLC DataFrame.
+/// same as the `series_from` function of the tradingview script
+///
+/// RSI and CCI are calculated from `close`, WaveTrend from `hlc3` and ADX from `high`, `low` and `close`.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame.
+/// * `feature` - The feature to calculate.
+///
+/// # Returns
+///
+/// The feature series, named after the feature and its parameters like `RSI_14_1`.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{feature_series, FeatureSpec};
+/// let df = df!(
+///     "high" => (0..50).map(|i| 101.0 + (i as f64).sin()).collect::<Vec<f64>>(),
+///     "low" => (0..50).map(|i| 99.0 + (i as f64).sin()).collect::<Vec<f64>>(),
+///     "close" => (0..50).map(|i| 100.0 + (i as f64).sin()).collect::<Vec<f64>>()
+/// ).unwrap();
+/// let rsi = feature_series(&df, &FeatureSpec::RSI { param_a: 14, param_b: 1 }).unwrap();
+/// println!("{:?}", rsi);
+/// ```
+pub fn feature_series(df: &DataFrame, feature: &FeatureSpec) -> Result<Series, Box<dyn std::error::Error>> {
+    feature.configuration_check();
+    let name = match *feature {
+        FeatureSpec::RSI { param_a, param_b } => format!("RSI_{}_{}", param_a, param_b),
+        FeatureSpec::WT { param_a, param_b } => format!("WT_{}_{}", param_a, param_b),
+        FeatureSpec::CCI { param_a, param_b } => format!("CCI_{}_{}", param_a, param_b),
+        FeatureSpec::ADX { param_a } => format!("ADX_{}", param_a),
+    };
+    Ok(Series::new(&name, feature_values(df, feature)?))
+}
+
+/// Calculates the feature space of the classifier from an OHLC DataFrame.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame.
+/// * `settings` - The settings holding the features and the feature count.
+///
+/// # Returns
+///
+/// A DataFrame with the `f1` to `fN` feature columns, `N` being `settings.feature_count`.
+///
+/// # Panics
+///
+/// When the settings are invalid, e.g. a `feature_count` outside 2 to 5.
+pub fn feature_frame(df: &DataFrame, settings: &Settings) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    settings.configuration_check();
+    let columns = settings.features[..settings.feature_count as usize]
+        .iter()
+        .enumerate()
+        .map(|(i, feature)| Ok(Series::new(&format!("f{}", i + 1), feature_values(df, feature)?)))
+        .collect::<Result<Vec<Series>, Box<dyn std::error::Error>>>()?;
+    Ok(DataFrame::new(columns)?)
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use crate::lorentzian_classification::types::DEFAULT_FEATURES;
+
+    #[test]
+    fn test_feature_series() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        for feature in DEFAULT_FEATURES.iter() {
+            let series = feature_series(&df, feature)?;
+            assert_eq!(series.len(), df.height());
+            // every feature is normalized to [0, 1] once warmed up
+            let values = helper::series_values(&series)?;
+            assert!(values[100..].iter().all(|v| (-1e-9..=1.0 + 1e-9).contains(v)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_feature_frame() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let settings = Settings {
+            source: "close",
+            neighbors_count: 8,
+            max_bars_back: 2000,
+            feature_count: 3,
+            features: DEFAULT_FEATURES,
+            show_exits: false,
+            use_dynamic_exits: false,
+            use_ema_filter: false,
+            ema_period: 200,
+            use_sma_filter: false,
+            sma_period: 200,
+        };
+        let frame = feature_frame(&df, &settings)?;
+        assert_eq!(frame.get_column_names(), vec!["f1", "f2", "f3"]);
+        eprintln!("{:?}", frame);
+        Ok(())
+    }
+
+    fn feature_count_settings(feature_count: i8) -> Settings<'static> {
+        Settings {
+            source: "close",
+            neighbors_count: 8,
+            max_bars_back: 2000,
+            feature_count,
+            features: DEFAULT_FEATURES,
+            show_exits: false,
+            use_dynamic_exits: false,
+            use_ema_filter: false,
+            ema_period: 200,
+            use_sma_filter: false,
+            sma_period: 200,
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "feature_count must be between 2 and 5")]
+    fn test_feature_frame_too_many_features() {
+        let _ = feature_frame(&example().unwrap(), &feature_count_settings(6));
+    }
+
+    #[test]
+    #[should_panic(expected = "feature_count must be between 2 and 5")]
+    fn test_feature_frame_negative_feature_count() {
+        let _ = feature_frame(&example().unwrap(), &feature_count_settings(-1));
+    }
+}
@@ -0,0 +1,347 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/13 09:55
+ * @Email: uyplayer@qq.com
+ * @File: filters
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! volatility, regime and adx filters of the pine `MLExtensions` library, and the ema / sma trend filters
+
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::types::{ConfigCheck, Filters, Settings};
+
+
+/// Volatility filter values, recent ATR(1) above historical ATR(10).
+pub(crate) fn volatility_values(high: &[f64], low: &[f64], close: &[f64], use_volatility_filter: bool) -> Vec<bool> {
+    if !use_volatility_filter {
+        return vec![true; high.len()];
+    }
+    let recent = helper::atr(high, low, close, 1);
+    let historical = helper::atr(high, low, close, 10);
+    recent.iter().zip(historical.iter()).map(|(r, h)| r > h).collect()
+}
+
+/// Regime filter values, the normalized slope decline of the KLMF curve of `src` is at least `threshold`.
+pub(crate) fn regime_values(src: &[f64], high: &[f64], low: &[f64], threshold: f64, use_regime_filter: bool) -> Vec<bool> {
+    let n = src.len();
+    if !use_regime_filter {
+        return vec![true; n];
+    }
+    let mut value1 = 0.0;
+    let mut value2 = 0.0;
+    let mut klmf = vec![0.0; n];
+    let mut abs_curve_slope = vec![f64::NAN; n];
+    for i in 0..n {
+        let prev_src = if i == 0 { f64::NAN } else { src[i - 1] };
+        let diff = src[i] - prev_src;
+        // pine `nz` keeps the recursion alive through the first bar
+        value1 = 0.2 * if diff.is_nan() { 0.0 } else { diff } + 0.8 * value1;
+        value2 = 0.1 * (high[i] - low[i]) + 0.8 * value2;
+        let omega = (value1 / value2).abs();
+        let alpha = (-omega.powi(2) + (omega.powi(4) + 16.0 * omega.powi(2)).sqrt()) / 8.0;
+        let prev_klmf = if i == 0 { 0.0 } else { klmf[i - 1] };
+        klmf[i] = alpha * src[i] + (1.0 - alpha) * prev_klmf;
+        if i > 0 {
+            abs_curve_slope[i] = (klmf[i] - klmf[i - 1]).abs();
+        }
+    }
+    let average = helper::ema(&abs_curve_slope, 200);
+    abs_curve_slope
+        .iter()
+        .zip(average.iter())
+        .map(|(slope, avg)| (slope - avg) / avg >= threshold)
+        .collect()
+}
+
+/// ADX filter values, ADX(14) of `src` above `threshold`.
+pub(crate) fn adx_values(high: &[f64], low: &[f64], src: &[f64], threshold: f64, use_adx_filter: bool) -> Vec<bool> {
+    if !use_adx_filter {
+        return vec![true; high.len()];
+    }
+    helper::adx(high, low, src, 14).iter().map(|&adx| adx > threshold).collect()
+}
+
+/// Volatility filter of the pine `MLExtensions` library.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame with `high`, `low` and `close` columns.
+/// * `use_volatility_filter` - Whether the filter is used, an unused filter is always true.
+///
+/// # Returns
+///
+/// The `filter_volatility` series, true when the recent ATR(1) is above the historical ATR(10).
+pub fn volatility_filter(df: &DataFrame, use_volatility_filter: bool) -> Result<Series, Box<dyn std::error::Error>> {
+    let high = helper::column_values(df, "high")?;
+    let low = helper::column_values(df, "low")?;
+    let close = helper::column_values(df, "close")?;
+    Ok(Series::new("filter_volatility", volatility_values(&high, &low, &close, use_volatility_filter)))
+}
+
+/// Regime filter of the pine `MLExtensions` library, calculated from `ohlc4`.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame with `open`, `high`, `low` and `close` columns.
+/// * `threshold` - The minimum normalized slope decline of the KLMF curve.
+/// * `use_regime_filter` - Whether the filter is used, an unused filter is always true.
+///
+/// # Returns
+///
+/// The `regime_filter` series, true when the market is trending.
+pub fn regime_filter(df: &DataFrame, threshold: f64, use_regime_filter: bool) -> Result<Series, Box<dyn std::error::Error>> {
+    let open = helper::column_values(df, "open")?;
+    let high = helper::column_values(df, "high")?;
+    let low = helper::column_values(df, "low")?;
+    let close = helper::column_values(df, "close")?;
+    let ohlc4: Vec<f64> = (0..close.len()).map(|i| (open[i] + high[i] + low[i] + close[i]) / 4.0).collect();
+    Ok(Series::new("regime_filter", regime_values(&ohlc4, &high, &low, threshold, use_regime_filter)))
+}
+
+/// ADX filter of the pine `MLExtensions` library.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame with `high`, `low` and the `source` columns.
+/// * `source` - The source column, usually close.
+/// * `threshold` - The minimum ADX(14).
+/// * `use_adx_filter` - Whether the filter is used, an unused filter is always true.
+///
+/// # Returns
+///
+/// The `filter_adx` series, true when the ADX is above the threshold.
+pub fn adx_filter(df: &DataFrame, source: &str, threshold: f64, use_adx_filter: bool) -> Result<Series, Box<dyn std::error::Error>> {
+    let high = helper::column_values(df, "high")?;
+    let low = helper::column_values(df, "low")?;
+    let src = helper::column_values(df, source)?;
+    Ok(Series::new("filter_adx", adx_values(&high, &low, &src, threshold, use_adx_filter)))
+}
+
+/// Trend filter values of a moving average, `(uptrend, downtrend)`: close above / below the average.
+pub(crate) fn trend_values(close: &[f64], average: &[f64], use_filter: bool) -> (Vec<bool>, Vec<bool>) {
+    if !use_filter {
+        return (vec![true; close.len()], vec![true; close.len()]);
+    }
+    let uptrend = close.iter().zip(average.iter()).map(|(c, a)| c > a).collect();
+    let downtrend = close.iter().zip(average.iter()).map(|(c, a)| c < a).collect();
+    (uptrend, downtrend)
+}
+
+/// EMA trend filter of the tradingview script.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame with a `close` column.
+/// * `period` - The period of the EMA.
+/// * `use_ema_filter` - Whether the filter is used, an unused filter is always true.
+///
+/// # Returns
+///
+/// A DataFrame with the `is_ema_uptrend` (close above the EMA) and `is_ema_downtrend` (close below the EMA) columns.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::ema_filter;
+/// let df = df!("close" => vec![1.0, 2.0, 3.0, 2.0, 1.0]).unwrap();
+/// let trend = ema_filter(&df, 2, true).unwrap();
+/// println!("{:?}", trend);
+/// ```
+pub fn ema_filter(df: &DataFrame, period: i32, use_ema_filter: bool) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    if period <= 1 {
+        return Err("period must be bigger than one".into());
+    }
+    let close = helper::column_values(df, "close")?;
+    let (uptrend, downtrend) = trend_values(&close, &helper::ema(&close, period as usize), use_ema_filter);
+    Ok(DataFrame::new(vec![Series::new("is_ema_uptrend", uptrend), Series::new("is_ema_downtrend", downtrend)])?)
+}
+
+/// SMA trend filter of the tradingview script.
+///
+/// # Arguments
+///
+/// * `df` - The input DataFrame with a `close` column.
+/// * `period` - The period of the SMA.
+/// * `use_sma_filter` - Whether the filter is used, an unused filter is always true.
+///
+/// # Returns
+///
+/// A DataFrame with the `is_sma_uptrend` (close above the SMA) and `is_sma_downtrend` (close below the SMA) columns.
+pub fn sma_filter(df: &DataFrame, period: i32, use_sma_filter: bool) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    if period <= 1 {
+        return Err("period must be bigger than one".into());
+    }
+    let close = helper::column_values(df, "close")?;
+    let (uptrend, downtrend) = trend_values(&close, &helper::sma(&close, period as usize), use_sma_filter);
+    Ok(DataFrame::new(vec![Series::new("is_sma_uptrend", uptrend), Series::new("is_sma_downtrend", downtrend)])?)
+}
+
+impl Settings<'_> {
+    /// Combines the EMA and SMA trend filters of the close values, `(uptrend, downtrend)`.
+    pub(crate) fn trend_mask(&self, close: &[f64]) -> (Vec<bool>, Vec<bool>) {
+        let (ema_up, ema_down) = trend_values(close, &helper::ema(close, self.ema_period as usize), self.use_ema_filter);
+        let (sma_up, sma_down) = trend_values(close, &helper::sma(close, self.sma_period as usize), self.use_sma_filter);
+        let uptrend = (0..close.len()).map(|i| ema_up[i] && sma_up[i]).collect();
+        let downtrend = (0..close.len()).map(|i| ema_down[i] && sma_down[i]).collect();
+        (uptrend, downtrend)
+    }
+
+    /// Applies the EMA and SMA trend filters of the settings to an OHLC DataFrame.
+    ///
+    /// # Arguments
+    ///
+    /// * `df` - The input DataFrame with a `close` column.
+    ///
+    /// # Returns
+    ///
+    /// A DataFrame with the `is_uptrend` column gating long entries and the `is_downtrend` column gating short entries.
+    pub fn trend_filter(&self, df: &DataFrame) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        self.configuration_check();
+        let close = helper::column_values(df, "close")?;
+        let (uptrend, downtrend) = self.trend_mask(&close);
+        Ok(DataFrame::new(vec![Series::new("is_uptrend", uptrend), Series::new("is_downtrend", downtrend)])?)
+    }
+}
+
+impl Filters {
+    /// Combines the used filters of the OHLC values, `src` is the source of the ADX filter.
+    pub(crate) fn mask(&self, open: &[f64], high: &[f64], low: &[f64], close: &[f64], src: &[f64]) -> Vec<bool> {
+        let ohlc4: Vec<f64> = (0..close.len()).map(|i| (open[i] + high[i] + low[i] + close[i]) / 4.0).collect();
+        let volatility = volatility_values(high, low, close, self.use_volatility_filter);
+        let regime = regime_values(&ohlc4, high, low, self.regime_threshold as f64, self.use_regime_filter);
+        let adx = adx_values(high, low, src, self.adx_threshold as f64, self.use_adx_filter);
+        (0..close.len()).map(|i| volatility[i] && regime[i] && adx[i]).collect()
+    }
+
+    /// Applies the volatility, regime and ADX filters to an OHLC DataFrame.
+    /// same as `filter.volatility and filter.regime and filter.adx` in the tradingview script
+    ///
+    /// # Arguments
+    ///
+    /// * `df` - The input DataFrame with `open`, `high`, `low`, `close` and the `source` columns.
+    /// * `source` - The source column of the ADX filter, usually close.
+    ///
+    /// # Returns
+    ///
+    /// The `filter_all` series, true when every used filter passes.
+    pub fn apply(&self, df: &DataFrame, source: &str) -> Result<Series, Box<dyn std::error::Error>> {
+        self.configuration_check();
+        let open = helper::column_values(df, "open")?;
+        let high = helper::column_values(df, "high")?;
+        let low = helper::column_values(df, "low")?;
+        let close = helper::column_values(df, "close")?;
+        let src = helper::column_values(df, source)?;
+        Ok(Series::new("filter_all", self.mask(&open, &high, &low, &close, &src)))
+    }
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+
+    /// Share of the bars after `warm_up` where the filter agrees with the tradingview column.
+    fn agreement(filter: &Series, expected: &Series, warm_up: usize) -> f64 {
+        let filter: Vec<bool> = filter.bool().unwrap().into_no_null_iter().collect();
+        let expected = helper::series_values(expected).unwrap();
+        let agree = (warm_up..filter.len()).filter(|&i| filter[i] == (expected[i] == 1.0)).count();
+        agree as f64 / (filter.len() - warm_up) as f64
+    }
+
+    #[test]
+    fn test_volatility_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let filter = volatility_filter(&df, true)?;
+        assert_eq!(agreement(&filter, df.column("filter_volatility")?, 100), 1.0);
+        assert!(volatility_filter(&df, false)?.bool()?.all());
+        Ok(())
+    }
+
+    #[test]
+    fn test_regime_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let filter = regime_filter(&df, -0.1, true)?;
+        // the ema of the slope needs a long warm up to forget the start of the data
+        assert!(agreement(&filter, df.column("regime_filter")?, 1000) > 0.99);
+        Ok(())
+    }
+
+    #[test]
+    fn test_adx_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let filter = adx_filter(&df, "close", 20.0, true)?;
+        assert_eq!(agreement(&filter, df.column("filter_adx")?, 100), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trend_filters() -> Result<(), Box<dyn std::error::Error>> {
+        let df = df!("close" => vec![1.0, 2.0, 3.0, 4.0, 3.0, 1.0, 0.5])?;
+        let ema = ema_filter(&df, 2, true)?;
+        let up: Vec<bool> = ema.column("is_ema_uptrend")?.bool()?.into_no_null_iter().collect();
+        let down: Vec<bool> = ema.column("is_ema_downtrend")?.bool()?.into_no_null_iter().collect();
+        // no trend before the average is warmed up
+        assert_eq!(up, vec![false, true, true, true, false, false, false]);
+        assert_eq!(down, vec![false, false, false, false, true, true, true]);
+        let sma = sma_filter(&df, 3, false)?;
+        assert!(sma.column("is_sma_uptrend")?.bool()?.all() && sma.column("is_sma_downtrend")?.bool()?.all());
+        assert!(ema_filter(&df, 1, true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings_trend_filter() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::lorentzian_classification::types::DEFAULT_FEATURES;
+        let df = example()?;
+        let settings = Settings {
+            source: "close",
+            neighbors_count: 8,
+            max_bars_back: 2000,
+            feature_count: 5,
+            features: DEFAULT_FEATURES,
+            show_exits: false,
+            use_dynamic_exits: false,
+            use_ema_filter: true,
+            ema_period: 200,
+            use_sma_filter: true,
+            sma_period: 200,
+        };
+        let trend = settings.trend_filter(&df)?;
+        let ema = ema_filter(&df, 200, true)?;
+        let sma = sma_filter(&df, 200, true)?;
+        let expected = ema.column("is_ema_uptrend")?.bool()? & sma.column("is_sma_uptrend")?.bool()?;
+        assert!(trend.column("is_uptrend")?.bool()?.equal(&expected).all());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let filters = Filters {
+            use_volatility_filter: true,
+            use_regime_filter: false,
+            use_adx_filter: true,
+            regime_threshold: -0.1,
+            adx_threshold: 20,
+        };
+        let volatility = volatility_filter(&df, true)?;
+        for source in ["close", "open"] {
+            let all = filters.apply(&df, source)?;
+            let adx = adx_filter(&df, source, 20.0, true)?;
+            assert!(all.bool()?.clone().into_no_null_iter()
+                .zip(volatility.bool()?.clone().into_no_null_iter().zip(adx.bool()?.clone().into_no_null_iter()))
+                .all(|(a, (v, x))| a == (v && x)));
+        }
+        assert!(filters.apply(&df, "missing").is_err());
+        Ok(())
+    }
+}
@@ -11,51 +11,46 @@
 
 //! Helper functions for data manipulation
 
-use polars::export::arrow::array::{Float64Array};
 use polars::prelude::*;
 
 
 /// Normalizes the values of the input series to a given range.
 ///
+/// The series can have any numeric dtype and any number of chunks, the values are cast to `Float64`.
+/// Nulls stay null and are ignored with `NaN` values when searching the minimum and maximum.
+///
 /// # Arguments
 /// * `src` - The input series
 /// * `min_val` - The minimum value of the range to normalize to
 /// * `max_val` - The maximum value of the range to normalize to
 ///
 /// # Returns
-/// The normalized series.s
-pub fn normalizer<'a>(src: &'a Series, min_val: f64, max_val: f64) -> Result<Series, Box<dyn std::error::Error>> {
-    let array = src.to_arrow(0);
-    let vec_values = match array.as_any().downcast_ref::<Float64Array>() {
-        Some(float_array) => {
-            let values: &[f64] = float_array.values();
-            let vec_values: Vec<f64> = values.to_vec();
-            vec_values
-        }
-        None => return Err("Failed to downcast to Float64Array or Int64Array".into()),
-    };
-    let actual_min_val = vec_values
-        .iter()
-        .min_by(|x, y| x.partial_cmp(y).unwrap())
+/// The normalized series, with the name of the input series.
+pub fn normalizer(src: &Series, min_val: f64, max_val: f64) -> Result<Series, Box<dyn std::error::Error>> {
+    let values = series_options(src)?;
+    let present = || values.iter().flatten().filter(|x| !x.is_nan());
+    let actual_min_val = present()
+        .copied()
+        .reduce(f64::min)
         .ok_or("Failed to find the minimum value in vec_values")?;
-
-    let actual_max_val = vec_values
-        .iter()
-        .max_by(|x, y| x.partial_cmp(y).unwrap())
+    let actual_max_val = present()
+        .copied()
+        .reduce(f64::max)
         .ok_or("Failed to find the maximum value in vec_values")?;
 
-    let scaled_values: Vec<f64> = vec_values
+    let scaled_values: Vec<Option<f64>> = values
         .iter()
-        .map(|&x| (x - actual_min_val) / (actual_max_val - actual_min_val) * (max_val - min_val) + min_val)
+        .map(|x| x.map(|x| (x - actual_min_val) / (actual_max_val - actual_min_val) * (max_val - min_val) + min_val))
         .collect();
 
-    Ok(Series::new("date", scaled_values))
-
-
+    Ok(Series::new(src.name(), scaled_values))
 }
 
 /// Rescales the values of the input series from one bounded range to another bounded range.
 ///
+/// The series can have any numeric dtype and any number of chunks, the values are cast to `Float64`
+/// and nulls stay null.
+///
 /// # Arguments
 /// * `src` - The input series
 /// * `old_min` - The minimum value of the range to rescale from
@@ -64,22 +59,14 @@ pub fn normalizer<'a>(src: &'a Series, min_val: f64, max_val: f64) -> Result<Ser
 /// * `new_max` - The maximum value of the range to rescale to
 ///
 /// # Returns
-/// The rescaled series
-pub  fn rescale<'a>(src: &'a Series, old_min: f64, old_max: f64, new_min: f64, new_max: f64) -> Result<Series, Box<dyn std::error::Error>>  {
-    let array = src.to_arrow(0);
-    let vec_values = match array.as_any().downcast_ref::<Float64Array>() {
-        Some(float_array) => {
-            let values: &[f64] = float_array.values();
-            let vec_values: Vec<f64> = values.to_vec();
-            vec_values
-        }
-        None => return Err("Failed to downcast to Float64Array or Int64Array".into()),
-    };
+/// The rescaled series, with the name of the input series.
+pub  fn rescale(src: &Series, old_min: f64, old_max: f64, new_min: f64, new_max: f64) -> Result<Series, Box<dyn std::error::Error>>  {
     let epsilon = 10e-10;
-    let vec_values = vec_values.iter()
-        .map(|x| new_min + (new_max - new_min) * (x - old_min) / f64::max(old_max - old_min, epsilon))
-        .collect::<Vec<f64>>();
-    Ok(Series::new("date", vec_values))
+    let vec_values = series_options(src)?
+        .iter()
+        .map(|x| x.map(|x| new_min + (new_max - new_min) * (x - old_min) / f64::max(old_max - old_min, epsilon)))
+        .collect::<Vec<Option<f64>>>();
+    Ok(Series::new(src.name(), vec_values))
 }
 
 
@@ -93,8 +80,10 @@ pub  fn rescale<'a>(src: &'a Series, old_min: f64, old_max: f64, new_min: f64, n
 ///
 /// # Returns
 ///
-/// The series containing the EWMA values.
-pub fn rma_indicator<'a>(src: &'a Series, length: i32)->Result<Series, Box<dyn std::error::Error>> {
+/// The series containing the EWMA values, with the name of the input series and one value per input value.
+/// A bar without a rolling mean, e.g. a leading null, is null.
+pub fn rma_indicator(src: &Series, length: i32)->Result<Series, Box<dyn std::error::Error>> {
+    let src = float_series(src)?;
     let duration = Duration::new(length.into());
     let options = RollingOptionsImpl {
         window_size: duration,
@@ -111,24 +100,294 @@ pub fn rma_indicator<'a>(src: &'a Series, length: i32)->Result<Series, Box<dyn s
 
     let alpha = 2.0 / (length as f64 + 1.0);
     let mut prev_ema: Option<f64> = None;
-    let mut ewma = Vec::new();
+    let mut ewma: Vec<Option<f64>> = Vec::with_capacity(rolling_mean.len());
     for opt in rolling_mean.f64()? {
+        // a bar without a rolling mean stays null and the average continues from the last value
         let val = match opt {
             Some(v) => v,
-            None => continue,
+            None => {
+                ewma.push(None);
+                continue;
+            }
         };
 
         let ema = match prev_ema {
             Some(prev) => alpha * val + (1.0 - alpha) * prev,
             None => val,
         };
-        ewma.push(ema);
+        ewma.push(Some(ema));
         prev_ema = Some(ema);
     }
-    let ewm_series = Series::new("date", ewma);
+    let ewm_series = Series::new(src.name(), ewma);
     Ok(ewm_series)
 }
 
+/// Casts a series of any numeric dtype to `Float64`, other dtypes are an error.
+///
+/// Integers above 2^53 lose precision and decimals are rounded to the nearest `f64`.
+pub(crate) fn float_series(src: &Series) -> Result<Series, Box<dyn std::error::Error>> {
+    let dtype = src.dtype();
+    if !dtype.is_numeric() && !matches!(dtype, DataType::Decimal(_, _)) {
+        return Err(format!("{} must be numeric, got {}", src.name(), dtype).into());
+    }
+    Ok(src.cast(&DataType::Float64)?)
+}
+
+/// Reads the values of the series as a vector of `f64`, nulls become `NaN`.
+///
+/// # Arguments
+/// * `src` - The input series
+///
+/// # Returns
+/// The series values
+pub(crate) fn series_values(src: &Series) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let values = float_series(src)?
+        .f64()?
+        .into_iter()
+        .map(|v| v.unwrap_or(f64::NAN))
+        .collect();
+    Ok(values)
+}
+
+/// Reads the values of the series as a vector of optional `f64`, nulls become `None`.
+pub(crate) fn series_options(src: &Series) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    Ok(float_series(src)?.f64()?.into_iter().collect())
+}
+
+/// Turns the `NaN` values into nulls.
+pub(crate) fn nan_to_null(values: &[f64]) -> Vec<Option<f64>> {
+    values.iter().map(|v| Some(*v).filter(|v| !v.is_nan())).collect()
+}
+
+/// Reads a column of the DataFrame as a vector of `f64`, nulls become `NaN`.
+pub(crate) fn column_values(df: &DataFrame, name: &str) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    series_values(df.column(name)?)
+}
+
+/// Simple moving average, equivalent to pine `ta.sma`.
+/// The first `length - 1` values are `NaN`.
+pub(crate) fn sma(src: &[f64], length: usize) -> Vec<f64> {
+    let mut out = vec![f64::NAN; src.len()];
+    if length == 0 {
+        return out;
+    }
+    for (i, window) in src.windows(length).enumerate() {
+        out[i + length - 1] = window.iter().sum::<f64>() / length as f64;
+    }
+    out
+}
+
+/// Moving average seeded with the sma of the first `length` values, as pine does for `ta.ema` and `ta.rma`.
+fn seeded_average(src: &[f64], length: usize, alpha: f64) -> Vec<f64> {
+    let seed = sma(src, length);
+    let mut prev = f64::NAN;
+    src.iter()
+        .zip(seed)
+        .map(|(&x, seed)| {
+            prev = if prev.is_nan() { seed } else { alpha * x + (1.0 - alpha) * prev };
+            prev
+        })
+        .collect()
+}
+
+/// Exponential moving average, equivalent to pine `ta.ema`.
+pub(crate) fn ema(src: &[f64], length: usize) -> Vec<f64> {
+    seeded_average(src, length, 2.0 / (length as f64 + 1.0))
+}
+
+/// Wilder's moving average, equivalent to pine `ta.rma`.
+/// Unlike [`rma_indicator`], the smoothing is `1 / length` and seeded with the first simple average.
+pub(crate) fn rma(src: &[f64], length: usize) -> Vec<f64> {
+    seeded_average(src, length, 1.0 / length as f64)
+}
+
+/// Relative strength index, equivalent to pine `ta.rsi`.
+pub(crate) fn rsi(src: &[f64], length: usize) -> Vec<f64> {
+    let mut up = vec![f64::NAN; src.len()];
+    let mut down = vec![f64::NAN; src.len()];
+    for i in 1..src.len() {
+        let change = src[i] - src[i - 1];
+        if !change.is_nan() {
+            up[i] = change.max(0.0);
+            down[i] = (-change).max(0.0);
+        }
+    }
+    let up = rma(&up, length);
+    let down = rma(&down, length);
+    up.iter()
+        .zip(down.iter())
+        .map(|(&u, &d)| {
+            if u.is_nan() || d.is_nan() {
+                f64::NAN
+            } else if d == 0.0 {
+                100.0
+            } else if u == 0.0 {
+                0.0
+            } else {
+                100.0 - 100.0 / (1.0 + u / d)
+            }
+        })
+        .collect()
+}
+
+/// Commodity channel index, equivalent to pine `ta.cci`.
+pub(crate) fn cci(src: &[f64], length: usize) -> Vec<f64> {
+    let mut out = vec![f64::NAN; src.len()];
+    if length == 0 {
+        return out;
+    }
+    for (i, window) in src.windows(length).enumerate() {
+        let mean = window.iter().sum::<f64>() / length as f64;
+        let deviation = window.iter().map(|x| (x - mean).abs()).sum::<f64>() / length as f64;
+        let last = window[length - 1];
+        out[i + length - 1] = (last - mean) / (0.015 * deviation);
+    }
+    out
+}
+
+/// Standard deviation, equivalent to pine `ta.stdev` (biased).
+/// The first `length - 1` values are `NaN`.
+pub(crate) fn stdev(src: &[f64], length: usize) -> Vec<f64> {
+    let mut out = vec![f64::NAN; src.len()];
+    if length == 0 {
+        return out;
+    }
+    for (i, window) in src.windows(length).enumerate() {
+        let mean = window.iter().sum::<f64>() / length as f64;
+        let variance = window.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / length as f64;
+        out[i + length - 1] = variance.sqrt();
+    }
+    out
+}
+
+/// Quantile of the standard normal distribution, Acklam's rational approximation (relative error below 1.2e-9).
+pub(crate) fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02, 1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+    const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02, 6.680131188771972e+01, -1.328068155288572e+01];
+    const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00, -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+    const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00, 3.754408661907416e+00];
+    let tail = |q: f64| (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0);
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// True range, equivalent to pine `ta.tr(true)`.
+pub(crate) fn true_range(high: &[f64], low: &[f64], close: &[f64]) -> Vec<f64> {
+    (0..high.len())
+        .map(|i| {
+            if i == 0 {
+                high[i] - low[i]
+            } else {
+                (high[i] - low[i])
+                    .max((high[i] - close[i - 1]).abs())
+                    .max((low[i] - close[i - 1]).abs())
+            }
+        })
+        .collect()
+}
+
+/// Average true range, equivalent to pine `ta.atr`.
+pub(crate) fn atr(high: &[f64], low: &[f64], close: &[f64], length: usize) -> Vec<f64> {
+    rma(&true_range(high, low, close), length)
+}
+
+/// Average directional index as calculated in the pine `MLExtensions` library,
+/// the directional movements are smoothed with wilder's running sum and the dx with `ta.rma`.
+pub(crate) fn adx(high: &[f64], low: &[f64], close: &[f64], length: usize) -> Vec<f64> {
+    let len = length as f64;
+    let mut tr_smooth = 0.0;
+    let mut plus_smooth = 0.0;
+    let mut minus_smooth = 0.0;
+    let mut dx = vec![f64::NAN; high.len()];
+    for i in 0..high.len() {
+        // pine `nz(x[1])` is zero on the first bar
+        let (prev_high, prev_low, prev_close) = if i == 0 { (0.0, 0.0, 0.0) } else { (high[i - 1], low[i - 1], close[i - 1]) };
+        let tr = (high[i] - low[i])
+            .max((high[i] - prev_close).abs())
+            .max((low[i] - prev_close).abs());
+        let up_move = high[i] - prev_high;
+        let down_move = prev_low - low[i];
+        let plus = if up_move > down_move { up_move.max(0.0) } else { 0.0 };
+        let minus = if down_move > up_move { down_move.max(0.0) } else { 0.0 };
+        tr_smooth = tr_smooth - tr_smooth / len + tr;
+        plus_smooth = plus_smooth - plus_smooth / len + plus;
+        minus_smooth = minus_smooth - minus_smooth / len + minus;
+        let di_plus = plus_smooth / tr_smooth * 100.0;
+        let di_minus = minus_smooth / tr_smooth * 100.0;
+        dx[i] = (di_plus - di_minus).abs() / (di_plus + di_minus) * 100.0;
+    }
+    rma(&dx, length)
+}
+
+/// Normalizes the values to a given range using the min and max seen so far,
+/// equivalent to `normalize` in the pine `MLExtensions` library.
+/// Unlike [`normalizer`], no future values are used, so it is safe for signals.
+pub(crate) fn historic_normalize(src: &[f64], min_val: f64, max_val: f64) -> Vec<f64> {
+    let mut historic_min = 10e10;
+    let mut historic_max = -10e10;
+    src.iter()
+        .map(|&x| {
+            if x.is_nan() {
+                return f64::NAN;
+            }
+            historic_min = f64::min(x, historic_min);
+            historic_max = f64::max(x, historic_max);
+            min_val + (max_val - min_val) * (x - historic_min) / f64::max(historic_max - historic_min, 10e-10)
+        })
+        .collect()
+}
+
+/// Rescales a slice from one bounded range to another using [`rescale`].
+fn rescale_values(src: &[f64], old_min: f64, old_max: f64, new_min: f64, new_max: f64) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let rescaled = rescale(&Series::new("data", src), old_min, old_max, new_min, new_max)?;
+    Ok(rescaled.f64()?.into_no_null_iter().collect())
+}
+
+/// Normalized RSI, equivalent to `n_rsi` in the pine `MLExtensions` library.
+pub(crate) fn n_rsi(close: &[f64], n1: usize, n2: usize) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    rescale_values(&ema(&rsi(close, n1), n2), 0.0, 100.0, 0.0, 1.0)
+}
+
+/// Normalized CCI, equivalent to `n_cci` in the pine `MLExtensions` library.
+pub(crate) fn n_cci(close: &[f64], n1: usize, n2: usize) -> Vec<f64> {
+    historic_normalize(&ema(&cci(close, n1), n2), 0.0, 1.0)
+}
+
+/// Normalized WaveTrend, equivalent to `n_wt` in the pine `MLExtensions` library.
+pub(crate) fn n_wt(hlc3: &[f64], n1: usize, n2: usize) -> Vec<f64> {
+    let ema1 = ema(hlc3, n1);
+    let distance: Vec<f64> = hlc3.iter().zip(ema1.iter()).map(|(x, e)| (x - e).abs()).collect();
+    let ema2 = ema(&distance, n1);
+    let ci: Vec<f64> = (0..hlc3.len()).map(|i| (hlc3[i] - ema1[i]) / (0.015 * ema2[i])).collect();
+    let wt1 = ema(&ci, n2);
+    let wt2 = sma(&wt1, 4);
+    let diff: Vec<f64> = wt1.iter().zip(wt2.iter()).map(|(a, b)| a - b).collect();
+    historic_normalize(&diff, 0.0, 1.0)
+}
+
+/// Normalized ADX, equivalent to `n_adx` in the pine `MLExtensions` library.
+pub(crate) fn n_adx(high: &[f64], low: &[f64], close: &[f64], n1: usize) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    rescale_values(&adx(high, low, close, n1), 0.0, 100.0, 0.0, 1.0)
+}
+
+/// The example OHLC data exported from tradingview with the values of the script, shared by the tests.
+#[cfg(test)]
+pub(crate) fn example() -> PolarsResult<DataFrame> {
+    use std::env;
+    use std::path::PathBuf;
+    let mut path = PathBuf::new();
+    path.push(env::current_dir().unwrap());
+    path.push("src/lorentzian_classification/data/BINANCE_BTCUSDT, 15 (1)rational_guesss.csv");
+    CsvReader::from_path(&path)?.infer_schema(None).has_header(true).finish()
+}
+
 #[cfg(test)]
 mod tests {
     use rand::Rng;
@@ -169,4 +428,44 @@ mod tests {
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_numeric_dtypes() -> Result<(), Box<dyn std::error::Error>> {
+        let expected = normalizer(&Series::new("close", vec![1.0, 2.0, 3.0, 5.0]), 0.0, 1.0)?;
+        // every chunk is processed
+        let mut chunked = Series::new("close", vec![1.0, 2.0]);
+        chunked.append(&Series::new("close", vec![3.0, 5.0]))?;
+        assert_eq!(chunked.n_chunks(), 2);
+        let decimal = Series::new("close", vec![1i64, 2, 3, 5]).cast(&DataType::Decimal(Some(10), Some(0)))?;
+        for src in [
+            Series::new("close", vec![1i64, 2, 3, 5]),
+            Series::new("close", vec![1.0f32, 2.0, 3.0, 5.0]),
+            Series::new("close", vec![1u32, 2, 3, 5]),
+            decimal,
+            chunked,
+        ] {
+            let res = normalizer(&src, 0.0, 1.0)?;
+            assert_eq!(res.name(), "close");
+            assert!(res.series_equal(&expected));
+            assert_eq!(rescale(&src, 0.0, 5.0, 0.0, 1.0)?.f64()?.get(3), Some(1.0));
+            assert_eq!(rma_indicator(&src, 2)?.name(), "close");
+        }
+        // nulls stay null
+        let with_null = normalizer(&Series::new("close", vec![Some(1.0), None, Some(3.0)]), 0.0, 1.0)?;
+        assert_eq!(with_null.f64()?.into_iter().collect::<Vec<Option<f64>>>(), vec![Some(0.0), None, Some(1.0)]);
+        let with_null = rma_indicator(&Series::new("close", vec![None, Some(1.0), Some(2.0), Some(3.0)]), 2)?;
+        assert_eq!(with_null.len(), 4);
+        assert_eq!(with_null.f64()?.get(0), None);
+        assert_eq!(with_null.f64()?.get(1), Some(1.0));
+        assert!(normalizer(&Series::new("close", vec!["a", "b"]), 0.0, 1.0).is_err());
+        assert!(rescale(&Series::new("close", vec![true, false]), 0.0, 1.0, 0.0, 1.0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_normal_quantile() {
+        assert!(normal_quantile(0.5).abs() < 1e-12);
+        assert!((normal_quantile(0.975) - 1.959963985).abs() < 1e-8);
+        assert!((normal_quantile(0.995) - 2.575829304).abs() < 1e-8);
+        assert!((normal_quantile(0.001) + 3.090232306).abs() < 1e-8);
+    }
+}
@@ -11,15 +11,16 @@
 
 
 
+use std::time::Duration;
 use polars::prelude::*;
-use polars::error::PolarsResult;
-use polars::frame::DataFrame;
-use polars::prelude::CsvReader;
-use std::time::Instant;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::regression::{kernel_regression, local_polynomial_regression, time_kernel_regression, LocalPolynomial, EpanechnikovKernel, GaussianKernel, LaplaceKernel, LocallyPeriodicKernel, PeriodicKernel, RationalQuadraticKernel};
 
 
 
 // kernel functions calculate rational quadratic curve , gaussian curve
+// missing values (null and NaN) are skipped and the weights of the others renormalized, the warm up bars are null
+// any numeric dtype is cast to f64 and the results keep the name of the input series
 
 
 /// Calculates the rational quadratic value for a given set of parameters.
@@ -28,6 +29,7 @@ use std::time::Instant;
 /// This function takes a series of values, a lookback period, a relative weight,
 /// and a starting point. It then calculates the rational quadratic value for each
 /// element in the series based on the provided parameters.
+/// Same as [`kernel_regression`] with a [`RationalQuadraticKernel`].
 ///
 /// # Arguments
 ///
@@ -50,36 +52,8 @@ use std::time::Instant;
 /// eprintln!("{:?}", result);
 /// println!("{:?}", result);
 /// ```
-pub fn rational_quadratic<'a>(src: &'a Series, look_back: i32, relative_weight: f32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error + 'a>> {
-    let size = (start_at_bar + 2) as usize;
-    let num_windows = src.len() - size + 1;
-    let windows: Vec<Series> = (0..num_windows)
-        .map(|i| {
-            src.slice(i as i64, (size) as i64 as usize)
-        })
-        .collect();
-    let weight: Vec<_> = (0..size)
-        .map(|i| {
-            let i_squared = (i as f64).powi(2);
-            (1.0 + i_squared / (f64::powi(look_back as f64, 2) * 2.0 * relative_weight as f64)).powf(-relative_weight as f64)
-        })
-        .collect();
-    let weight = Series::new("data", weight);
-    let current_weight: Vec<f64> = (0..num_windows).map(|i| {
-        let reversed_window = windows[i].reverse();
-        let weighted_sum = reversed_window.multiply(&weight).unwrap();
-        weighted_sum.sum().expect("weighted_sum collection error")
-    }).collect();
-    let current_weight = Series::new("data", current_weight);
-    let cumulative_weight: Vec<f64> = (0..num_windows).map(|_| {
-        weight.sum().expect("cumulative_weight collection error")
-    }).collect();
-    let cumulative_weight = Series::new("data", cumulative_weight);
-    let kernel_line = current_weight.divide(&cumulative_weight)?;
-    let zero = vec![0.0; size - 1];
-    let mut previous = Series::new("data", zero);
-    let kernel_line = (*previous.extend(&kernel_line)?).clone().into();
-    Ok(kernel_line)
+pub fn rational_quadratic(src: &Series, look_back: i32, relative_weight: f32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    kernel_regression(src, &RationalQuadraticKernel { look_back: look_back as f64, relative_weight: relative_weight as f64 }, start_at_bar)
 }
 
 /// Calculates the rational quadratic value for a given set of parameters.
@@ -109,23 +83,31 @@ pub fn rational_quadratic<'a>(src: &'a Series, look_back: i32, relative_weight:
 /// let result = rational_quadratic_tv(&src, 2, 3.0, 1);
 /// println!("{:?}", result);
 /// ```
-pub fn rational_quadratic_tv<'a>(src: &'a Series, look_back: i32, relative_weight: f32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error + 'a>> {
-    let mut val = vec![0.0; src.len()];
+pub fn rational_quadratic_tv(src: &Series, look_back: i32, relative_weight: f32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    let src = &helper::float_series(src)?;
+    let mut val: Vec<Option<f64>> = vec![None; src.len()];
 
     for bar_index in (start_at_bar + 1)..src.len() as i32 {
         let mut current_weight = 0.0;
         let mut cumulative_weight = 0.0;
 
         for i in 0..(start_at_bar + 2) {
-            let y: AnyValue = src.get((bar_index - i) as usize).expect("loop error");
+            // missing values are skipped
+            let y = match src.get((bar_index - i) as usize)? {
+                AnyValue::Null => continue,
+                y => y.try_extract::<f64>()?,
+            };
+            if y.is_nan() {
+                continue;
+            }
             let w = (1.0 + (i.pow(2) as f64) / (look_back.pow(2) as f64 * 2.0 * relative_weight as f64)).powf(-relative_weight as f64);
-            current_weight += y.try_extract::<f64>().expect("casting error") * w;
+            current_weight += y * w;
             cumulative_weight += w;
         }
-        val[bar_index as usize] = current_weight / cumulative_weight;
+        val[bar_index as usize] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
     }
 
-    Ok(Series::new("data", val))
+    Ok(Series::new(src.name(), val))
 }
 
 /// Performs a Gaussian operation on a given time series.
@@ -134,6 +116,7 @@ pub fn rational_quadratic_tv<'a>(src: &'a Series, look_back: i32, relative_weigh
 /// This function calculates a Gaussian operation on the input time series data.
 /// The Gaussian operation is performed using a specified look-back window
 /// and starting point in the time series.
+/// Same as [`kernel_regression`] with a [`GaussianKernel`].
 ///
 /// # Arguments
 ///
@@ -154,43 +137,8 @@ pub fn rational_quadratic_tv<'a>(src: &'a Series, look_back: i32, relative_weigh
 /// let result = gaussian(&src, 2, 3);
 /// println!("{:?}", result);
 /// ```
-///
-
-
-pub fn gaussian<'a >(src: &'a Series, look_back: i32, start_at_bar: i32)->Result<Series,Box<dyn std::error::Error + 'a>> {
-    let size = start_at_bar + 2;
-    let num_windows = src.len() - size as usize + 1;
-    let windows: Vec<Series> = (0..num_windows).map(|i| {
-        src.slice(i as i64, size as usize)
-    }).collect();
-
-    let mut weight: Vec<f64> = Vec::new();
-    for i in 0..size {
-        let weight_val = (-((i as f64).powi(2)) / (2.0 * (look_back.pow(2)) as f64)).exp();
-        weight.push(weight_val);
-    }
-    let weight = Series::new("data", weight);
-
-    let current_weight : Vec<f64>= (0..num_windows).map(|i| {
-        let reversed_window = windows[i].reverse();
-        let weighted_sum = reversed_window.multiply(&weight).unwrap();
-        weighted_sum.sum().expect("cumulative_weight collection error")
-    }).collect();
-    let current_weight = Series::new("data", current_weight);
-
-    let cumulative_weight: Vec<f64> = (0..num_windows).map(|_| {
-        weight.sum().expect("cumulative_weight collection error")
-    }).collect();
-    let cumulative_weight = Series::new("data", cumulative_weight);
-
-    let gaussian_line = current_weight.divide(&cumulative_weight)?;
-
-
-    let zero = vec![0.0; (size - 1) as usize];
-    let mut previous = Series::new("data", zero);
-    let gaussian_line = (*previous.extend(&gaussian_line)?).clone().into();
-    Ok(gaussian_line)
-
+pub fn gaussian(src: &Series, look_back: i32, start_at_bar: i32)->Result<Series,Box<dyn std::error::Error>> {
+    kernel_regression(src, &GaussianKernel { look_back: look_back as f64 }, start_at_bar)
 }
 
 /// Performs a Gaussian operation on a given time series using a different approach.
@@ -219,22 +167,463 @@ pub fn gaussian<'a >(src: &'a Series, look_back: i32, start_at_bar: i32)->Result
 /// let result = gaussian_tv(&src, 2, 3);
 /// println!("{:?}", result);
 /// ```
-///
-pub fn gaussian_tv<'a >(src: &'a Series, look_back: i32, start_at_bar: i32)->Result<Series,Box<dyn std::error::Error + 'a>> {
-    let mut val = vec![0.0; src.len()];
+pub fn gaussian_tv(src: &Series, look_back: i32, start_at_bar: i32)->Result<Series,Box<dyn std::error::Error>> {
+    let src = &helper::float_series(src)?;
+    let mut val: Vec<Option<f64>> = vec![None; src.len()];
     for bar_index in (start_at_bar + 1)..src.len() as i32 {
 
         let mut current_weight = 0.0;
         let mut cumulative_weight = 0.0;
         for i in 0..start_at_bar+2{
-            let y = src.get((bar_index - i) as usize)?;
+            // missing values are skipped
+            let y = match src.get((bar_index - i) as usize)? {
+                AnyValue::Null => continue,
+                y => y.try_extract::<f64>()?,
+            };
+            if y.is_nan() {
+                continue;
+            }
             let w =   ((-(i.pow(2) as f64)) / (2.0 * (look_back.pow(2) as f64))).exp();
-            current_weight += y.try_extract::<f64>().expect("casting error") * w;
+            current_weight += y * w;
+            cumulative_weight += w;
+        }
+        val[bar_index as usize] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
+    }
+    Ok(Series::new(src.name(), val))
+}
+
+
+/// Performs a Periodic kernel regression on a given time series.
+/// improved version from formula in tradingview.com
+///
+/// This function calculates the periodic kernel estimate of the input time series data,
+/// the weights repeat every `period` bars so that the estimate follows seasonal patterns.
+/// Same as [`kernel_regression`] with a [`PeriodicKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the look-back value for the periodic operation.
+/// * `period` - An integer representing the distance between repetitions.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the periodic kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::periodic;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = periodic(&src, 2, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn periodic(src: &Series, look_back: i32, period: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    kernel_regression(src, &PeriodicKernel { look_back: look_back as f64, period: period as f64 }, start_at_bar)
+}
+
+/// Performs a Periodic kernel regression on a given time series.
+/// original version in in tradingview.com
+///
+/// This function calculates the periodic kernel estimate of the input time series data
+/// bar by bar, with the loop of the tradingview library.
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the look-back value for the periodic operation.
+/// * `period` - An integer representing the distance between repetitions.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the periodic kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::periodic_tv;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = periodic_tv(&src, 2, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn periodic_tv(src: &Series, look_back: i32, period: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    let src = &helper::float_series(src)?;
+    let mut val: Vec<Option<f64>> = vec![None; src.len()];
+    for bar_index in (start_at_bar + 1)..src.len() as i32 {
+        let mut current_weight = 0.0;
+        let mut cumulative_weight = 0.0;
+        for i in 0..start_at_bar + 2 {
+            // missing values are skipped
+            let y = match src.get((bar_index - i) as usize)? {
+                AnyValue::Null => continue,
+                y => y.try_extract::<f64>()?,
+            };
+            if y.is_nan() {
+                continue;
+            }
+            let w = (-2.0 * (std::f64::consts::PI * i as f64 / period as f64).sin().powi(2) / (look_back.pow(2) as f64)).exp();
+            current_weight += y * w;
+            cumulative_weight += w;
+        }
+        val[bar_index as usize] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
+    }
+    Ok(Series::new(src.name(), val))
+}
+
+/// Performs a Locally Periodic kernel regression on a given time series.
+/// improved version from formula in tradingview.com
+///
+/// This function calculates the locally periodic kernel estimate of the input time series data,
+/// the product of the periodic and the gaussian kernels, so that repetitions fade with the distance.
+/// Same as [`kernel_regression`] with a [`LocallyPeriodicKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the look-back value for the locally periodic operation.
+/// * `period` - An integer representing the distance between repetitions.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the locally periodic kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::locally_periodic;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = locally_periodic(&src, 2, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn locally_periodic(src: &Series, look_back: i32, period: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    kernel_regression(src, &LocallyPeriodicKernel { look_back: look_back as f64, period: period as f64 }, start_at_bar)
+}
+
+/// Performs a Locally Periodic kernel regression on a given time series.
+/// original version in in tradingview.com
+///
+/// This function calculates the locally periodic kernel estimate of the input time series data
+/// bar by bar, with the loop of the tradingview library.
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the look-back value for the locally periodic operation.
+/// * `period` - An integer representing the distance between repetitions.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the locally periodic kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::locally_periodic_tv;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = locally_periodic_tv(&src, 2, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn locally_periodic_tv(src: &Series, look_back: i32, period: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    let src = &helper::float_series(src)?;
+    let mut val: Vec<Option<f64>> = vec![None; src.len()];
+    for bar_index in (start_at_bar + 1)..src.len() as i32 {
+        let mut current_weight = 0.0;
+        let mut cumulative_weight = 0.0;
+        for i in 0..start_at_bar + 2 {
+            // missing values are skipped
+            let y = match src.get((bar_index - i) as usize)? {
+                AnyValue::Null => continue,
+                y => y.try_extract::<f64>()?,
+            };
+            if y.is_nan() {
+                continue;
+            }
+            let periodic = (-2.0 * (std::f64::consts::PI * i as f64 / period as f64).sin().powi(2) / (look_back.pow(2) as f64)).exp();
+            let w = periodic * ((-(i.pow(2) as f64)) / (2.0 * (look_back.pow(2) as f64))).exp();
+            current_weight += y * w;
+            cumulative_weight += w;
+        }
+        val[bar_index as usize] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
+    }
+    Ok(Series::new(src.name(), val))
+}
+
+/// Performs an Epanechnikov kernel regression on a given time series.
+/// improved version
+///
+/// This function calculates the Epanechnikov kernel estimate of the input time series data,
+/// `0.75 * (1 - (i / look_back)^2)`, the values `look_back` or more bars back get no weight.
+/// Same as [`kernel_regression`] with an [`EpanechnikovKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the bandwidth of the kernel.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the Epanechnikov kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::epanechnikov;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = epanechnikov(&src, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn epanechnikov(src: &Series, look_back: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    kernel_regression(src, &EpanechnikovKernel { look_back: look_back as f64 }, start_at_bar)
+}
+
+/// Performs an Epanechnikov kernel regression on a given time series.
+/// original version, same loop as the tradingview library
+///
+/// This function calculates the Epanechnikov kernel estimate of the input time series data
+/// bar by bar.
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the bandwidth of the kernel.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the Epanechnikov kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::epanechnikov_tv;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = epanechnikov_tv(&src, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn epanechnikov_tv(src: &Series, look_back: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    let src = &helper::float_series(src)?;
+    let mut val: Vec<Option<f64>> = vec![None; src.len()];
+    for bar_index in (start_at_bar + 1)..src.len() as i32 {
+        let mut current_weight = 0.0;
+        let mut cumulative_weight = 0.0;
+        for i in 0..start_at_bar + 2 {
+            // missing values are skipped
+            let y = match src.get((bar_index - i) as usize)? {
+                AnyValue::Null => continue,
+                y => y.try_extract::<f64>()?,
+            };
+            if y.is_nan() {
+                continue;
+            }
+            let u = i as f64 / look_back as f64;
+            let w = if u < 1.0 { 0.75 * (1.0 - u.powi(2)) } else { 0.0 };
+            current_weight += y * w;
             cumulative_weight += w;
         }
-        val[bar_index  as usize] = current_weight / cumulative_weight;
+        val[bar_index as usize] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
     }
-    Ok(Series::new("data", val))
+    Ok(Series::new(src.name(), val))
+}
+
+/// Performs a Laplace kernel regression on a given time series.
+/// improved version
+///
+/// This function calculates the Laplace kernel estimate of the input time series data,
+/// `exp(-i / look_back)`, the weights decay exponentially with the distance.
+/// Same as [`kernel_regression`] with a [`LaplaceKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the bandwidth of the kernel.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the Laplace kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::laplace;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = laplace(&src, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn laplace(src: &Series, look_back: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    kernel_regression(src, &LaplaceKernel { look_back: look_back as f64 }, start_at_bar)
+}
+
+/// Performs a Laplace kernel regression on a given time series.
+/// original version, same loop as the tradingview library
+///
+/// This function calculates the Laplace kernel estimate of the input time series data
+/// bar by bar.
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the bandwidth of the kernel.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the Laplace kernel estimate, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::laplace_tv;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = laplace_tv(&src, 2, 3);
+/// println!("{:?}", result);
+/// ```
+pub fn laplace_tv(src: &Series, look_back: i32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    let src = &helper::float_series(src)?;
+    let mut val: Vec<Option<f64>> = vec![None; src.len()];
+    for bar_index in (start_at_bar + 1)..src.len() as i32 {
+        let mut current_weight = 0.0;
+        let mut cumulative_weight = 0.0;
+        for i in 0..start_at_bar + 2 {
+            // missing values are skipped
+            let y = match src.get((bar_index - i) as usize)? {
+                AnyValue::Null => continue,
+                y => y.try_extract::<f64>()?,
+            };
+            if y.is_nan() {
+                continue;
+            }
+            let w = (-(i as f64) / look_back as f64).exp();
+            current_weight += y * w;
+            cumulative_weight += w;
+        }
+        val[bar_index as usize] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
+    }
+    Ok(Series::new(src.name(), val))
+}
+
+/// Same as [`local_polynomial_regression`] with a [`RationalQuadraticKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the look-back period for the calculation.
+/// * `relative_weight` - A floating-point value representing the relative weight.
+/// * `start_at_bar` - An integer representing the starting bar for the calculation.
+/// * `degree` - The degree of the local polynomial, `LocalPolynomial::Constant` is [`rational_quadratic`].
+///
+/// # Returns
+///
+/// A Result containing a new Series with the calculated values, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{rational_quadratic_local, LocalPolynomial};
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = rational_quadratic_local(&src, 2, 3.0, 1, LocalPolynomial::Linear);
+/// println!("{:?}", result);
+/// ```
+pub fn rational_quadratic_local(src: &Series, look_back: i32, relative_weight: f32, start_at_bar: i32, degree: LocalPolynomial) -> Result<Series, Box<dyn std::error::Error>> {
+    local_polynomial_regression(src, &RationalQuadraticKernel { look_back: look_back as f64, relative_weight: relative_weight as f64 }, start_at_bar, degree)
+}
+
+/// Same as [`local_polynomial_regression`] with a [`GaussianKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `look_back` - An integer representing the look-back value for the Gaussian operation.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+/// * `degree` - The degree of the local polynomial, `LocalPolynomial::Constant` is [`gaussian`].
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the Gaussian operation result, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{gaussian_local, LocalPolynomial};
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = gaussian_local(&src, 2, 2, LocalPolynomial::Quadratic);
+/// println!("{:?}", result);
+/// ```
+pub fn gaussian_local(src: &Series, look_back: i32, start_at_bar: i32, degree: LocalPolynomial) -> Result<Series, Box<dyn std::error::Error>> {
+    local_polynomial_regression(src, &GaussianKernel { look_back: look_back as f64 }, start_at_bar, degree)
+}
+
+/// Same as [`time_kernel_regression`] with a [`RationalQuadraticKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `time` - The timestamps of the values, a datetime or a date column.
+/// * `bandwidth` - The bandwidth of the kernel, the time counterpart of `look_back`.
+/// * `relative_weight` - A floating-point value representing the relative weight.
+/// * `start_at_bar` - An integer representing the starting bar for the calculation.
+///
+/// # Returns
+///
+/// A Result containing a new Series with the calculated values, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use polars::prelude::*;
+/// use tech_analysis::rational_quadratic_time;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let time = Series::new("time", vec![0i64, 60_000, 3_660_000, 3_720_000, 3_780_000]).cast(&DataType::Datetime(TimeUnit::Milliseconds, None)).unwrap();
+/// let result = rational_quadratic_time(&src, &time, Duration::from_secs(120), 3.0, 1);
+/// println!("{:?}", result);
+/// ```
+pub fn rational_quadratic_time(src: &Series, time: &Series, bandwidth: Duration, relative_weight: f32, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    let relative_weight = relative_weight as f64;
+    time_kernel_regression(src, time, bandwidth, |look_back| RationalQuadraticKernel { look_back, relative_weight }, start_at_bar)
+}
+
+/// Same as [`time_kernel_regression`] with a [`GaussianKernel`].
+///
+/// # Arguments
+///
+/// * `src` - A reference to the input Series containing the data.
+/// * `time` - The timestamps of the values, a datetime or a date column.
+/// * `bandwidth` - The bandwidth of the kernel, the time counterpart of `look_back`.
+/// * `start_at_bar` - An integer representing the starting point in the time series.
+///
+/// # Returns
+///
+/// A Result containing a new Series representing the Gaussian operation result, or an error.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use polars::prelude::*;
+/// use tech_analysis::gaussian_time;
+/// let src = Series::new("data",vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let time = Series::new("time", vec![0i64, 60_000, 3_660_000, 3_720_000, 3_780_000]).cast(&DataType::Datetime(TimeUnit::Milliseconds, None)).unwrap();
+/// let result = gaussian_time(&src, &time, Duration::from_secs(120), 1);
+/// println!("{:?}", result);
+/// ```
+pub fn gaussian_time(src: &Series, time: &Series, bandwidth: Duration, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    time_kernel_regression(src, time, bandwidth, |look_back| GaussianKernel { look_back }, start_at_bar)
 }
 
 
@@ -242,18 +631,9 @@ pub fn gaussian_tv<'a >(src: &'a Series, look_back: i32, start_at_bar: i32)->Res
 #[cfg(test)]
 mod tests {
     use std::error::Error;
+    use std::time::Instant;
     use super::*;
-
-    fn example() -> PolarsResult<DataFrame> {
-        use std::env;
-        use std::path::PathBuf;
-        let mut path = PathBuf::new();
-        path.push(env::current_dir().unwrap());
-        path.push("src/lorentzian_classification/data/BINANCE_BTCUSDT, 15 (1)rational_guesss.csv");
-        let path = PathBuf::from(path);
-        let data = CsvReader::from_path(&path)?.infer_schema(None).has_header(true).finish();
-        data
-    }
+    use crate::lorentzian_classification::helper::example;
 
     #[test]
     fn test_rational_quadratic() {
@@ -263,7 +643,7 @@ mod tests {
         // println!("{:?}", binding.describe(None));
         let close = binding.column("close").unwrap();
         let start1 = Instant::now();
-        let kernel_line = rational_quadratic(&close, 8, 1.0, 25);
+        let kernel_line = rational_quadratic(close, 8, 1.0, 25);
         let duration = start1.elapsed();
         println!("Time elapsed in expensive_function() is: {:?}", duration);
         let rational_quadratic = binding.column("rational_quadratic").unwrap();
@@ -273,7 +653,7 @@ mod tests {
         let mean_squared_error = squared_errors.slice(27, squared_errors.len() - 27).sum_as_series() / (squared_errors.len() - 27);
         println!("Mean Squared Error: {}", mean_squared_error);
         let _start2 = Instant::now();
-        let kernel_line_tv = rational_quadratic_tv(&close, 8, 1.0, 25);
+        let kernel_line_tv = rational_quadratic_tv(close, 8, 1.0, 25);
         let duration = start1.elapsed();
         println!("Time elapsed in expensive_function() is: {:?}", duration);
         // Calculate the squared differences between predicted and actual values
@@ -291,7 +671,7 @@ mod tests {
         // println!("{:?}", binding.describe(None));
         let close = binding.column("close").unwrap();
         let start1 = Instant::now();
-        let g = gaussian(&close, 16, 25);
+        let g = gaussian(close, 16, 25);
         let duration = start1.elapsed();
         println!("Time elapsed in gaussian func is: {:?}", duration);
         let gt = binding.column("gaussian").unwrap();
@@ -301,7 +681,7 @@ mod tests {
         let mean_squared_error = squared_errors.slice(27, squared_errors.len() - 27).sum_as_series() / (squared_errors.len() - 27);
         println!("Mean Squared Error: {}", mean_squared_error);
         let _start2 = Instant::now();
-        let gtv = gaussian_tv(&close, 16, 25);
+        let gtv = gaussian_tv(close, 16, 25);
         let duration = start1.elapsed();
         println!("Time elapsed in gaussian_tv func is: {:?}", duration);
         // Calculate the squared differences between predicted and actual values
@@ -315,4 +695,129 @@ mod tests {
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_same_as_tv() -> Result<(), Box<dyn Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        let pairs = [
+            (rational_quadratic(close, 8, 8.0, 25)?, rational_quadratic_tv(close, 8, 8.0, 25)?),
+            (gaussian(close, 16, 25)?, gaussian_tv(close, 16, 25)?),
+            (periodic(close, 8, 12, 25)?, periodic_tv(close, 8, 12, 25)?),
+            (locally_periodic(close, 8, 12, 25)?, locally_periodic_tv(close, 8, 12, 25)?),
+            (epanechnikov(close, 16, 25)?, epanechnikov_tv(close, 16, 25)?),
+            (laplace(close, 8, 25)?, laplace_tv(close, 8, 25)?),
+        ];
+        for (fast, tv) in pairs {
+            assert_eq!(fast.len(), tv.len());
+            for (a, b) in fast.f64()?.into_iter().zip(tv.f64()?) {
+                match (a, b) {
+                    (Some(a), Some(b)) => assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{} != {}", a, b),
+                    (a, b) => assert_eq!(a, b),
+                }
+            }
+            // the warm up bars are null
+            assert_eq!(fast.null_count(), 26);
+        }
+        assert!(gaussian(&close.slice(0, 10), 16, 25).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_kernel_family() -> Result<(), Box<dyn Error>> {
+        let src = Series::new("data", vec![2.0; 40]);
+        // a constant series is its own estimate for every kernel
+        for line in [periodic(&src, 8, 12, 25)?, locally_periodic(&src, 8, 12, 25)?, epanechnikov(&src, 4, 25)?, laplace(&src, 8, 25)?] {
+            assert!(line.f64()?.into_no_null_iter().skip(26).all(|v| (v - 2.0).abs() < 1e-12));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_values() -> Result<(), Box<dyn Error>> {
+        let values: Vec<Option<f64>> = (0..40).map(|i| if i == 30 { None } else { Some(2.0) }).collect();
+        let src = Series::new("data", values);
+        let with_nan = Series::new("data", (0..40).map(|i| if i == 30 { f64::NAN } else { 2.0 }).collect::<Vec<f64>>());
+        // missing values are skipped and the weights renormalized, in both versions
+        for line in [rational_quadratic(&src, 8, 8.0, 25)?, rational_quadratic_tv(&src, 8, 8.0, 25)?, gaussian(&with_nan, 8, 25)?, gaussian_tv(&with_nan, 8, 25)?] {
+            assert_eq!(line.null_count(), 26);
+            assert!(line.f64()?.into_iter().skip(26).all(|v| (v.unwrap() - 2.0).abs() < 1e-12));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_polynomial() -> Result<(), Box<dyn Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        // the local constant fit is the kernel regression
+        assert!(rational_quadratic_local(close, 8, 8.0, 25, LocalPolynomial::Constant)?.series_equal_missing(&rational_quadratic(close, 8, 8.0, 25)?));
+        assert!(gaussian_local(close, 6, 25, LocalPolynomial::Constant)?.series_equal_missing(&gaussian(close, 6, 25)?));
+        // a linear fit follows a line without lag, a quadratic fit a parabola
+        let line = Series::new("data", (0..60).map(|i| 3.0 * i as f64 + 1.0).collect::<Vec<f64>>());
+        let parabola = Series::new("data", (0..60).map(|i| (i * i) as f64 - 4.0 * i as f64).collect::<Vec<f64>>());
+        let fits = [
+            (&line, rational_quadratic_local(&line, 8, 8.0, 25, LocalPolynomial::Linear)?),
+            (&line, gaussian_local(&line, 6, 25, LocalPolynomial::Quadratic)?),
+            (&parabola, gaussian_local(&parabola, 6, 25, LocalPolynomial::Quadratic)?),
+        ];
+        for (src, fit) in fits {
+            assert_eq!(fit.null_count(), 26);
+            for (y, estimate) in src.f64()?.into_no_null_iter().zip(fit.f64()?).skip(26) {
+                assert!((y - estimate.unwrap()).abs() < 1e-6 * y.abs().max(1.0), "{} != {:?}", y, estimate);
+            }
+        }
+        let lagging = gaussian(&line, 6, 25)?;
+        assert!((lagging.f64()?.get(59).unwrap() - 178.0).abs() > 1.0);
+        // the higher degrees track the price closer
+        let error = |fit: Series| -> Result<f64, Box<dyn Error>> {
+            let difference = (&fit - close).slice(26, close.len() - 26);
+            Ok(difference.f64()?.into_no_null_iter().map(|d| d.abs()).sum::<f64>())
+        };
+        let constant = error(rational_quadratic(close, 8, 8.0, 25)?)?;
+        let linear = error(rational_quadratic_local(close, 8, 8.0, 25, LocalPolynomial::Linear)?)?;
+        let quadratic = error(rational_quadratic_local(close, 8, 8.0, 25, LocalPolynomial::Quadratic)?)?;
+        assert!(quadratic < linear && linear < constant);
+        // a single weighted value can not fit a line
+        let short = Series::new("data", vec![1.0, f64::NAN, 3.0, 4.0]);
+        assert_eq!(gaussian_local(&short, 2, 0, LocalPolynomial::Linear)?.null_count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_kernels() -> Result<(), Box<dyn Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        // 15 minute bars, a bandwidth of look_back bars is the bar offset kernel
+        let time = Series::new("time", (0..close.len() as i64).map(|i| i * 900_000).collect::<Vec<i64>>()).cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+        let pairs = [
+            (rational_quadratic_time(close, &time, Duration::from_secs(8 * 900), 8.0, 25)?, rational_quadratic(close, 8, 8.0, 25)?),
+            (gaussian_time(close, &time, Duration::from_secs(16 * 900), 25)?, gaussian(close, 16, 25)?),
+        ];
+        for (by_time, by_bar) in pairs {
+            assert_eq!(by_time.null_count(), 26);
+            for (a, b) in by_time.f64()?.into_iter().zip(by_bar.f64()?).skip(26) {
+                assert!((a.unwrap() - b.unwrap()).abs() <= 1e-9 * b.unwrap().abs());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_dtypes() -> Result<(), Box<dyn Error>> {
+        let values: Vec<i64> = (0..40).map(|i| i % 7).collect();
+        let expected = rational_quadratic(&Series::new("close", values.iter().map(|v| *v as f64).collect::<Vec<f64>>()), 8, 8.0, 25)?;
+        let mut chunked = Series::new("close", &values[..20]);
+        chunked.append(&Series::new("close", &values[20..]))?;
+        for src in [Series::new("close", &values), chunked] {
+            let line = rational_quadratic(&src, 8, 8.0, 25)?;
+            assert_eq!(line.name(), "close");
+            assert!(line.series_equal_missing(&expected));
+            let line_tv = rational_quadratic_tv(&src, 8, 8.0, 25)?;
+            assert_eq!(line_tv.name(), "close");
+            assert_eq!(line_tv.null_count(), 26);
+        }
+        assert!(gaussian(&Series::new("close", vec!["a"; 40]), 8, 25).is_err());
+        assert!(gaussian_tv(&Series::new("close", vec!["a"; 40]), 8, 25).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/12 16:40
+ * @Email: uyplayer@qq.com
+ * @File: labels
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! training label generators for lorentzian classification
+//!
+//! The label of bar `t` is always the outcome of a window that ends at `t`,
+//! so a label never uses bars after the one it is attached to and can be used in backtests.
+
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::types::{ConfigCheck, Direction};
+
+
+/// A generator of training labels for the classifier.
+pub trait Labeler {
+    /// Labels every bar of the source series.
+    ///
+    /// # Arguments
+    ///
+    /// * `src` - The source series, usually close.
+    ///
+    /// # Returns
+    ///
+    /// The `label` series holding a `Direction` value for every bar.
+    fn label(&self, src: &Series) -> Result<Series, Box<dyn std::error::Error>> {
+        let labels: Vec<i32> = self.directions(&helper::series_values(src)?).into_iter().map(|d| d as i32).collect();
+        Ok(Series::new("label", labels))
+    }
+
+    /// Labels every bar of the source values.
+    fn directions(&self, src: &[f64]) -> Vec<Direction>;
+}
+
+/// Which direction a rise of the source is labelled with.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum LabelOrientation {
+    /// A rise is `SHORT` and a fall is `LONG`, as the training labels of the tradingview script.
+    #[default]
+    RiseIsShort,
+    /// A rise is `LONG` and a fall is `SHORT`, as [`TripleBarrierLabeler`] and [`TrendScanningLabeler`].
+    RiseIsLong,
+}
+
+/// Fixed-horizon labels, the default labels of the tradingview script.
+///
+/// Bar `t` is a rise when `src[t] - src[t - horizon]` is above `neutral_band * |src[t - horizon]|`,
+/// a fall when it is below the opposite, and `NEUTRAL` otherwise. The band scales with the absolute
+/// base value, so a zero or negative base is handled without a division.
+/// Same as pine with the default `RiseIsShort` orientation, a horizon of 4 and no band this is
+/// `src[4] < src[0] ? short : src[4] > src[0] ? long : neutral`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedHorizonLabeler {
+    /// The number of bars between the compared values.
+    pub horizon: usize,
+    /// The change below which the bar is `NEUTRAL`, relative to `|src[t - horizon]|`.
+    pub neutral_band: f64,
+    /// Which direction a rise is labelled with.
+    pub orientation: LabelOrientation,
+}
+
+impl Default for FixedHorizonLabeler {
+    fn default() -> Self {
+        FixedHorizonLabeler { horizon: 4, neutral_band: 0.0, orientation: LabelOrientation::RiseIsShort }
+    }
+}
+
+impl ConfigCheck for FixedHorizonLabeler {
+    type Output = ();
+    fn configuration_check(&self) -> Self::Output {
+        if self.horizon == 0 {
+            panic!("horizon must be bigger than zero");
+        }
+        if self.neutral_band < 0.0 {
+            panic!("neutral_band must not be negative");
+        }
+    }
+}
+
+impl Labeler for FixedHorizonLabeler {
+    fn directions(&self, src: &[f64]) -> Vec<Direction> {
+        self.configuration_check();
+        let (rise, fall) = match self.orientation {
+            LabelOrientation::RiseIsShort => (Direction::SHORT, Direction::LONG),
+            LabelOrientation::RiseIsLong => (Direction::LONG, Direction::SHORT),
+        };
+        (0..src.len())
+            .map(|t| {
+                if t < self.horizon {
+                    return Direction::NEUTRAL;
+                }
+                let base = src[t - self.horizon];
+                let change = src[t] - base;
+                let band = self.neutral_band * base.abs();
+                if change > band {
+                    rise
+                } else if change < -band {
+                    fall
+                } else {
+                    Direction::NEUTRAL
+                }
+            })
+            .collect()
+    }
+}
+
+/// Triple-barrier labels.
+///
+/// A position is opened at `src[t - time_limit]`, bar `t` is labelled `LONG` when the profit target
+/// is reached first, `SHORT` when the stop is reached first, and `NEUTRAL` when the time limit
+/// is hit before any of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TripleBarrierLabeler {
+    /// The relative move of the upper barrier, `0.01` is 1%.
+    pub profit_target: f64,
+    /// The relative move of the lower barrier, `0.01` is 1%.
+    pub stop_loss: f64,
+    /// The number of bars of the vertical barrier.
+    pub time_limit: usize,
+}
+
+impl ConfigCheck for TripleBarrierLabeler {
+    type Output = ();
+    fn configuration_check(&self) -> Self::Output {
+        if self.profit_target <= 0.0 {
+            panic!("profit_target must be bigger than zero");
+        }
+        if self.stop_loss <= 0.0 {
+            panic!("stop_loss must be bigger than zero");
+        }
+        if self.time_limit == 0 {
+            panic!("time_limit must be bigger than zero");
+        }
+    }
+}
+
+impl Labeler for TripleBarrierLabeler {
+    fn directions(&self, src: &[f64]) -> Vec<Direction> {
+        self.configuration_check();
+        (0..src.len())
+            .map(|t| {
+                if t < self.time_limit {
+                    return Direction::NEUTRAL;
+                }
+                let entry = src[t - self.time_limit];
+                let upper = entry * (1.0 + self.profit_target);
+                let lower = entry * (1.0 - self.stop_loss);
+                src[t - self.time_limit + 1..=t]
+                    .iter()
+                    .find_map(|&price| {
+                        if price >= upper {
+                            Some(Direction::LONG)
+                        } else if price <= lower {
+                            Some(Direction::SHORT)
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(Direction::NEUTRAL)
+            })
+            .collect()
+    }
+}
+
+/// Trend-scanning labels.
+///
+/// For every window length between `min_horizon` and `max_horizon` ending at bar `t`, a linear trend
+/// is fitted to the values and the t-value of its slope is calculated. The bar is labelled with the
+/// sign of the most significant slope, or `NEUTRAL` when its absolute t-value is below `min_t_value`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrendScanningLabeler {
+    /// The shortest window length, at least 3.
+    pub min_horizon: usize,
+    /// The longest window length.
+    pub max_horizon: usize,
+    /// The absolute t-value below which the bar is `NEUTRAL`.
+    pub min_t_value: f64,
+}
+
+impl ConfigCheck for TrendScanningLabeler {
+    type Output = ();
+    fn configuration_check(&self) -> Self::Output {
+        if self.min_horizon < 3 {
+            panic!("min_horizon must be at least 3");
+        }
+        if self.max_horizon < self.min_horizon {
+            panic!("max_horizon must not be smaller than min_horizon");
+        }
+        if self.min_t_value < 0.0 {
+            panic!("min_t_value must not be negative");
+        }
+    }
+}
+
+/// The t-value of the slope of the least squares line through the values.
+fn slope_t_value(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<f64>() / n;
+    let sxx: f64 = (0..values.len()).map(|i| (i as f64 - mean_x).powi(2)).sum();
+    let sxy: f64 = values.iter().enumerate().map(|(i, y)| (i as f64 - mean_x) * (y - mean_y)).sum();
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let sse: f64 = values.iter().enumerate().map(|(i, y)| (y - intercept - slope * i as f64).powi(2)).sum();
+    let standard_error = (sse / (n - 2.0) / sxx).sqrt();
+    if standard_error == 0.0 {
+        // a perfect line is infinitely significant
+        return if slope == 0.0 { 0.0 } else { slope.signum() * f64::INFINITY };
+    }
+    slope / standard_error
+}
+
+impl Labeler for TrendScanningLabeler {
+    fn directions(&self, src: &[f64]) -> Vec<Direction> {
+        self.configuration_check();
+        (0..src.len())
+            .map(|t| {
+                let best = (self.min_horizon..=self.max_horizon.min(t + 1))
+                    .map(|length| slope_t_value(&src[t + 1 - length..=t]))
+                    .filter(|t_value| !t_value.is_nan())
+                    .max_by(|a, b| a.abs().partial_cmp(&b.abs()).unwrap());
+                match best {
+                    Some(t_value) if t_value.abs() >= self.min_t_value && t_value > 0.0 => Direction::LONG,
+                    Some(t_value) if t_value.abs() >= self.min_t_value && t_value < 0.0 => Direction::SHORT,
+                    _ => Direction::NEUTRAL,
+                }
+            })
+            .collect()
+    }
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_horizon_labeler() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("close", vec![1.0, 2.0, 3.0, 4.0, 5.0, 1.0, 5.0, 4.0]);
+        let labels = FixedHorizonLabeler::default().label(&src)?;
+        let labels: Vec<i32> = labels.i32()?.into_no_null_iter().collect();
+        // close[4] < close is short, close[4] > close is long, as in pine
+        assert_eq!(labels, vec![0, 0, 0, 0, -1, 1, -1, 0]);
+
+        let banded = FixedHorizonLabeler { horizon: 1, neutral_band: 0.3, ..Default::default() };
+        assert_eq!(banded.directions(&[1.0, 1.2, 2.0, 1.0]), vec![Direction::NEUTRAL, Direction::NEUTRAL, Direction::SHORT, Direction::LONG]);
+        // the band scales with the absolute base, a rise from a negative base is still a rise
+        assert_eq!(banded.directions(&[-1.0, -0.5, -0.6]), vec![Direction::NEUTRAL, Direction::SHORT, Direction::NEUTRAL]);
+        // a zero base has no band
+        assert_eq!(banded.directions(&[0.0, 0.1, 0.0, 0.0]), vec![Direction::NEUTRAL, Direction::SHORT, Direction::LONG, Direction::NEUTRAL]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_horizon_pine_rule() {
+        // without a band the labels are the pine rule, `src[t - 4] < src[t]` is short
+        let src: Vec<f64> = (0..200).map(|i| (i as f64 / 7.0).sin() * 10.0 + (i % 3) as f64).collect();
+        let expected: Vec<Direction> = (0..src.len())
+            .map(|t| match t {
+                t if t < 4 => Direction::NEUTRAL,
+                t if src[t - 4] < src[t] => Direction::SHORT,
+                t if src[t - 4] > src[t] => Direction::LONG,
+                _ => Direction::NEUTRAL,
+            })
+            .collect();
+        assert_eq!(FixedHorizonLabeler::default().directions(&src), expected);
+
+        // the trend orientation labels a rise long, as the other labelers
+        let trend = FixedHorizonLabeler { orientation: LabelOrientation::RiseIsLong, ..Default::default() };
+        let flipped: Vec<Direction> = expected.iter().map(|d| match d {
+            Direction::LONG => Direction::SHORT,
+            Direction::SHORT => Direction::LONG,
+            Direction::NEUTRAL => Direction::NEUTRAL,
+        }).collect();
+        assert_eq!(trend.directions(&src), flipped);
+        let barrier = TripleBarrierLabeler { profit_target: 0.1, stop_loss: 0.1, time_limit: 4 };
+        let rise = [1.0, 1.0, 1.0, 1.0, 2.0];
+        assert_eq!(trend.directions(&rise)[4], barrier.directions(&rise)[4]);
+    }
+
+    #[test]
+    fn test_triple_barrier_labeler() {
+        let labeler = TripleBarrierLabeler { profit_target: 0.1, stop_loss: 0.1, time_limit: 2 };
+        let src = [100.0, 111.0, 95.0, 89.0, 90.0, 91.0];
+        assert_eq!(
+            labeler.directions(&src),
+            vec![Direction::NEUTRAL, Direction::NEUTRAL, Direction::LONG, Direction::SHORT, Direction::NEUTRAL, Direction::NEUTRAL]
+        );
+    }
+
+    #[test]
+    fn test_trend_scanning_labeler() {
+        let labeler = TrendScanningLabeler { min_horizon: 3, max_horizon: 5, min_t_value: 2.0 };
+        let up: Vec<f64> = (0..10).map(|i| i as f64 + if i % 2 == 0 { 0.1 } else { 0.0 }).collect();
+        let down: Vec<f64> = up.iter().map(|x| -x).collect();
+        let flat = [1.0, 2.0, 1.0, 2.0, 1.0, 2.0];
+        assert_eq!(labeler.directions(&up)[9], Direction::LONG);
+        assert_eq!(labeler.directions(&down)[9], Direction::SHORT);
+        assert_eq!(labeler.directions(&flat)[5], Direction::NEUTRAL);
+        // not enough bars for the shortest window
+        assert_eq!(labeler.directions(&up)[1], Direction::NEUTRAL);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_trend_scanning_configuration() {
+        TrendScanningLabeler { min_horizon: 2, max_horizon: 5, min_t_value: 2.0 }.directions(&[1.0]);
+    }
+}
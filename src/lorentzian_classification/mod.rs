@@ -13,10 +13,42 @@
 mod kernel;
 mod types;
 mod helper;
-
-pub use kernel::{rational_quadratic,rational_quadratic_tv,gaussian,gaussian_tv};
-pub use types::{Settings,Filters,KernelFilter,Direction};
+mod classification;
+mod features;
+mod ann;
+mod labels;
+mod filters;
+mod trade;
+mod signals;
+mod stats;
+mod regression;
+mod stream;
+mod envelope;
+mod expressions;
+mod bandwidth;
+mod dynamics;
+mod adaptive;
+mod confidence;
+
+pub use kernel::{rational_quadratic,rational_quadratic_tv,gaussian,gaussian_tv,periodic,periodic_tv,locally_periodic,locally_periodic_tv,epanechnikov,epanechnikov_tv,laplace,laplace_tv,rational_quadratic_local,gaussian_local,rational_quadratic_time,gaussian_time};
+pub use regression::{Kernel,TimeKernel,kernel_regression,kernel_regression_with_policy,NanPolicy,Lookahead,kernel_smoothing,LocalPolynomial,local_polynomial_regression,time_kernel_regression,RationalQuadraticKernel,GaussianKernel,PeriodicKernel,LocallyPeriodicKernel,EpanechnikovKernel,LaplaceKernel};
+pub use stream::{KernelStream,RationalQuadraticStream,GaussianStream};
+pub use envelope::{Envelope,nadaraya_watson_envelope};
+pub use expressions::{TaExpr,TaNameSpace};
+pub use bandwidth::{CrossValidation,BandwidthSelection,select_bandwidth};
+pub use dynamics::{TurningPointKind,TurningPoint,kernel_dynamics,turning_points};
+pub use adaptive::{VolatilityMeasure,AdaptiveBandwidth,adaptive_kernel_regression,rational_quadratic_adaptive,gaussian_adaptive};
+pub use confidence::kernel_confidence;
+pub use types::{ConfigCheck,Settings,Filters,KernelFilter,Direction,FeatureSpec,DEFAULT_FEATURES};
 pub use helper::{normalizer,rescale,rma_indicator};
+pub use classification::{lorentzian_classification,lorentzian_classification_with_labeler,explain_classification,Explanation};
+pub use features::{feature_series,feature_frame};
+pub use ann::{LorentzianAnn,Neighbors,lorentzian_distance};
+pub use labels::{Labeler,LabelOrientation,FixedHorizonLabeler,TripleBarrierLabeler,TrendScanningLabeler};
+pub use filters::{volatility_filter,regime_filter,adx_filter,ema_filter,sma_filter};
+pub use trade::{ExitMode,ExitReason,TradeEventKind,TradeEvent,TradeBar,PositionStateMachine,trade_events};
+pub use signals::kernel_signals;
+pub use stats::TradeStats;
 
 
 
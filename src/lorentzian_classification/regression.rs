@@ -0,0 +1,739 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/20 11:30
+ * @Email: uyplayer@qq.com
+ * @File: regression
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! generic Nadaraya-Watson kernel regression and the kernels of the tradingview library
+
+use std::f64::consts::PI;
+use std::time::Duration;
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+
+
+/// A kernel of the Nadaraya-Watson regression.
+///
+/// The estimate of a bar is the average of the last values weighted by their distance in bars.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{kernel_regression, Kernel};
+/// // the last 3 values weigh the same
+/// struct Boxcar;
+/// impl Kernel for Boxcar {
+///     fn weight(&self, distance: usize) -> f64 {
+///         if distance < 3 { 1.0 } else { 0.0 }
+///     }
+/// }
+/// let src = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = kernel_regression(&src, &Boxcar, 2).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub trait Kernel {
+    /// The weight of the value `distance` bars back.
+    fn weight(&self, distance: usize) -> f64;
+}
+
+/// A kernel that can weight a value at a continuous distance, the time distance of [`time_kernel_regression`].
+///
+/// The distance is in the unit of the bandwidth the kernel was built with, seconds for
+/// [`time_kernel_regression`], so it is no bar offset and cannot be passed to [`Kernel::weight`].
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use polars::prelude::*;
+/// use tech_analysis::{time_kernel_regression, Kernel, TimeKernel};
+/// // the values of the last `width` seconds weigh the same
+/// struct Boxcar { width: f64 }
+/// impl Kernel for Boxcar {
+///     fn weight(&self, distance: usize) -> f64 {
+///         self.weight_at(distance as f64)
+///     }
+/// }
+/// impl TimeKernel for Boxcar {
+///     fn weight_at(&self, distance: f64) -> f64 {
+///         if distance < self.width { 1.0 } else { 0.0 }
+///     }
+/// }
+/// let src = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let time = Series::new("time", vec![0i64, 60_000, 120_000, 180_000, 240_000])
+///     .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+///     .unwrap();
+/// let result = time_kernel_regression(&src, &time, Duration::from_secs(150), |width| Boxcar { width }, 2).unwrap();
+/// assert_eq!(result.f64().unwrap().get(4), Some(4.0));
+/// ```
+pub trait TimeKernel: Kernel {
+    /// The weight of a value `distance` away, in the unit of the bandwidth.
+    fn weight_at(&self, distance: f64) -> f64;
+}
+
+/// The rational quadratic kernel, `(1 + d^2 / (2 * relative_weight * look_back^2))^-relative_weight`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RationalQuadraticKernel {
+    /// The bandwidth of the kernel.
+    pub look_back: f64,
+    /// The relative weighting of time frames, the kernel is a gaussian as it grows.
+    pub relative_weight: f64,
+}
+
+impl Kernel for RationalQuadraticKernel {
+    fn weight(&self, distance: usize) -> f64 {
+        self.weight_at(distance as f64)
+    }
+}
+
+impl TimeKernel for RationalQuadraticKernel {
+    fn weight_at(&self, distance: f64) -> f64 {
+        let d_squared = distance.powi(2);
+        (1.0 + d_squared / (f64::powi(self.look_back, 2) * 2.0 * self.relative_weight)).powf(-self.relative_weight)
+    }
+}
+
+/// The gaussian kernel, `exp(-d^2 / (2 * look_back^2))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GaussianKernel {
+    /// The bandwidth of the kernel.
+    pub look_back: f64,
+}
+
+impl Kernel for GaussianKernel {
+    fn weight(&self, distance: usize) -> f64 {
+        self.weight_at(distance as f64)
+    }
+}
+
+impl TimeKernel for GaussianKernel {
+    fn weight_at(&self, distance: f64) -> f64 {
+        (-(distance.powi(2)) / (2.0 * self.look_back.powi(2))).exp()
+    }
+}
+
+/// The periodic kernel, `exp(-2 * sin(pi * d / period)^2 / look_back^2)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeriodicKernel {
+    /// The bandwidth of the kernel.
+    pub look_back: f64,
+    /// The distance between repetitions.
+    pub period: f64,
+}
+
+impl Kernel for PeriodicKernel {
+    fn weight(&self, distance: usize) -> f64 {
+        self.weight_at(distance as f64)
+    }
+}
+
+impl TimeKernel for PeriodicKernel {
+    fn weight_at(&self, distance: f64) -> f64 {
+        (-2.0 * (PI * distance / self.period).sin().powi(2) / self.look_back.powi(2)).exp()
+    }
+}
+
+/// The locally periodic kernel, the product of the periodic and the gaussian kernels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocallyPeriodicKernel {
+    /// The bandwidth of the kernel.
+    pub look_back: f64,
+    /// The distance between repetitions.
+    pub period: f64,
+}
+
+impl Kernel for LocallyPeriodicKernel {
+    fn weight(&self, distance: usize) -> f64 {
+        self.weight_at(distance as f64)
+    }
+}
+
+impl TimeKernel for LocallyPeriodicKernel {
+    fn weight_at(&self, distance: f64) -> f64 {
+        PeriodicKernel { look_back: self.look_back, period: self.period }.weight_at(distance)
+            * GaussianKernel { look_back: self.look_back }.weight_at(distance)
+    }
+}
+
+/// The Epanechnikov kernel, `0.75 * (1 - (d / look_back)^2)`, zero from `look_back` bars back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EpanechnikovKernel {
+    /// The bandwidth of the kernel.
+    pub look_back: f64,
+}
+
+impl Kernel for EpanechnikovKernel {
+    fn weight(&self, distance: usize) -> f64 {
+        self.weight_at(distance as f64)
+    }
+}
+
+impl TimeKernel for EpanechnikovKernel {
+    fn weight_at(&self, distance: f64) -> f64 {
+        let u = distance / self.look_back;
+        if u < 1.0 { 0.75 * (1.0 - u.powi(2)) } else { 0.0 }
+    }
+}
+
+/// The Laplace kernel, `exp(-d / look_back)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LaplaceKernel {
+    /// The bandwidth of the kernel.
+    pub look_back: f64,
+}
+
+impl Kernel for LaplaceKernel {
+    fn weight(&self, distance: usize) -> f64 {
+        self.weight_at(distance as f64)
+    }
+}
+
+impl TimeKernel for LaplaceKernel {
+    fn weight_at(&self, distance: f64) -> f64 {
+        (-distance / self.look_back).exp()
+    }
+}
+
+/// The weights of the distances `0..size`.
+pub(crate) fn kernel_weights<K: Kernel + ?Sized>(kernel: &K, size: usize) -> Vec<f64> {
+    (0..size).map(|distance| kernel.weight(distance)).collect()
+}
+
+/// How the kernels handle the `NaN` values of the source, nulls are always skipped.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum NanPolicy {
+    /// A `NaN` in the window makes the estimate `NaN`.
+    Propagate,
+    /// `NaN` values are skipped like nulls.
+    #[default]
+    Skip,
+    /// A `NaN` in the source is an error.
+    Error,
+}
+
+/// Reads the values of the series for the kernels, `None` values are skipped.
+pub(crate) fn kernel_values(src: &Series, nan_policy: NanPolicy) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    let values = helper::series_options(src)?;
+    match nan_policy {
+        NanPolicy::Propagate => Ok(values),
+        NanPolicy::Skip => Ok(values.into_iter().map(|v| v.filter(|v| !v.is_nan())).collect()),
+        NanPolicy::Error => match values.iter().position(|v| v.is_some_and(f64::is_nan)) {
+            Some(index) => Err(format!("NaN value at index {} of {}", index, src.name()).into()),
+            None => Ok(values),
+        },
+    }
+}
+
+/// Weighted average of a window, `window` holds the values from the current bar back and `weights[i]` is the
+/// weight of the value `i` bars back. Missing values are skipped and the weights of the others renormalized.
+pub(crate) fn window_average<'a, I>(window: I, weights: &[f64], cumulative_weight: f64, complete: bool) -> Option<f64>
+where
+    I: Iterator<Item = &'a Option<f64>>,
+{
+    if complete {
+        let current_weight: f64 = window.zip(weights).map(|(y, w)| y.unwrap() * w).sum();
+        return Some(current_weight / cumulative_weight);
+    }
+    let (current_weight, cumulative_weight) = window
+        .zip(weights)
+        .filter_map(|(y, w)| y.map(|y| (y * w, *w)))
+        .fold((0.0, 0.0), |(c, t), (y, w)| (c + y, t + w));
+    if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) }
+}
+
+/// Weighted average of the last `weights.len()` values of every bar, `weights[i]` is the weight of the value `i` bars back.
+/// single pass over the values, the first `weights.len() - 1` bars and the bars without any value are `None`
+pub(crate) fn kernel_convolve(src: &[Option<f64>], weights: &[f64]) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    let size = weights.len();
+    if size == 0 || src.len() < size {
+        return Err(format!("at least {} values are required by the kernel, got {}", size, src.len()).into());
+    }
+    let cumulative_weight: f64 = weights.iter().sum();
+    let mut kernel_line = vec![None; src.len()];
+    // number of missing values in the window
+    let mut missing = src[..size - 1].iter().filter(|v| v.is_none()).count();
+    for bar_index in size - 1..src.len() {
+        let first = bar_index + 1 - size;
+        missing += src[bar_index].is_none() as usize;
+        kernel_line[bar_index] = window_average(src[first..=bar_index].iter().rev(), weights, cumulative_weight, missing == 0);
+        missing -= src[first].is_none() as usize;
+    }
+    Ok(kernel_line)
+}
+
+/// Calculates the Nadaraya-Watson kernel regression of a series with any kernel.
+///
+/// The estimate of every bar is the average of the last `start_at_bar + 2` values weighted by `kernel`,
+/// same window as the kernel functions of the tradingview library. The weights are calculated once
+/// and the values are convolved in a single pass, the first `start_at_bar + 1` bars are null.
+/// Nulls and `NaN` values are skipped and the weights of the remaining values renormalized,
+/// see [`kernel_regression_with_policy`] to handle `NaN` values differently.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `kernel` - The kernel weighting the values by their distance.
+/// * `start_at_bar` - The starting point for the calculation.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{kernel_regression, GaussianKernel};
+/// let src = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = kernel_regression(&src, &GaussianKernel { look_back: 2.0 }, 3).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn kernel_regression<K: Kernel + ?Sized>(src: &Series, kernel: &K, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>> {
+    kernel_regression_with_policy(src, kernel, start_at_bar, NanPolicy::default())
+}
+
+/// Calculates the Nadaraya-Watson kernel regression of a series with a `NaN` policy.
+///
+/// Same as [`kernel_regression`], nulls are skipped and `NaN` values are handled by `nan_policy`.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `kernel` - The kernel weighting the values by their distance.
+/// * `start_at_bar` - The starting point for the calculation.
+/// * `nan_policy` - Whether `NaN` values propagate, are skipped or are an error.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{kernel_regression_with_policy, GaussianKernel, NanPolicy};
+/// let src = Series::new("data", vec![1.0, 2.0, f64::NAN, 4.0, 5.0]);
+/// let kernel = GaussianKernel { look_back: 2.0 };
+/// assert!(kernel_regression_with_policy(&src, &kernel, 1, NanPolicy::Error).is_err());
+/// let result = kernel_regression_with_policy(&src, &kernel, 1, NanPolicy::Skip).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn kernel_regression_with_policy<K: Kernel + ?Sized>(src: &Series, kernel: &K, start_at_bar: i32, nan_policy: NanPolicy) -> Result<Series, Box<dyn std::error::Error>> {
+    if start_at_bar < 0 {
+        return Err("start_at_bar must not be negative".into());
+    }
+    let weights = kernel_weights(kernel, (start_at_bar + 2) as usize);
+    let values = kernel_values(src, nan_policy)?;
+    Ok(Series::new(src.name(), kernel_convolve(&values, &weights)?))
+}
+
+/// The degree of the polynomial fitted in every window by [`local_polynomial_regression`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum LocalPolynomial {
+    /// A weighted average, the Nadaraya-Watson estimate of [`kernel_regression`].
+    #[default]
+    Constant,
+    /// A weighted line, no lag on linear trends.
+    Linear,
+    /// A weighted parabola, no lag on quadratic trends and less overshoot at turning points.
+    Quadratic,
+}
+
+impl LocalPolynomial {
+    /// The degree of the polynomial.
+    pub fn degree(&self) -> usize {
+        match self {
+            LocalPolynomial::Constant => 0,
+            LocalPolynomial::Linear => 1,
+            LocalPolynomial::Quadratic => 2,
+        }
+    }
+}
+
+/// The intercept of the weighted least squares polynomial of a window, `window` holds the values
+/// from the current bar back and `x` is the distance in bars. `None` when the fit is singular.
+fn local_intercept<'a, I>(window: I, weights: &[f64], degree: usize) -> Option<f64>
+where
+    I: Iterator<Item = &'a Option<f64>>,
+{
+    let size = degree + 1;
+    // normal equations [X'WX | X'Wy]
+    let mut system = [[0.0; 4]; 3];
+    for (distance, (y, w)) in window.zip(weights).enumerate() {
+        let Some(y) = y else { continue };
+        let powers = [1.0, distance as f64, (distance * distance) as f64];
+        for row in 0..size {
+            for col in 0..size {
+                system[row][col] += w * powers[row] * powers[col];
+            }
+            system[row][size] += w * powers[row] * y;
+        }
+    }
+    let scale = system.iter().flat_map(|row| row[..size].iter()).fold(0.0_f64, |m, v| m.max(v.abs()));
+    // gaussian elimination with partial pivoting
+    for pivot in 0..size {
+        let best = (pivot..size).max_by(|a, b| system[*a][pivot].abs().total_cmp(&system[*b][pivot].abs()))?;
+        if system[best][pivot].abs() <= scale * 1e-12 {
+            return None;
+        }
+        system.swap(pivot, best);
+        let pivot_row = system[pivot];
+        for row in system[pivot + 1..size].iter_mut() {
+            let factor = row[pivot] / pivot_row[pivot];
+            for (value, pivot_value) in row[pivot..=size].iter_mut().zip(&pivot_row[pivot..=size]) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+    let mut coefficients = [0.0; 3];
+    for row in (0..size).rev() {
+        let known: f64 = (row + 1..size).map(|col| system[row][col] * coefficients[col]).sum();
+        coefficients[row] = (system[row][size] - known) / system[row][row];
+    }
+    Some(coefficients[0])
+}
+
+/// Calculates the local polynomial kernel regression of a series.
+///
+/// Fits a polynomial to the last `start_at_bar + 2` values by least squares weighted by `kernel`,
+/// the estimate of the bar is the value of the polynomial at the bar. The local constant fit is
+/// [`kernel_regression`], the local linear and quadratic fits follow trends without the lag of the
+/// weighted average. Nulls and `NaN` values are skipped, the bars where the fit is singular
+/// (fewer weighted values than coefficients) are null as well as the first `start_at_bar + 1` bars.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `kernel` - The kernel weighting the values by their distance.
+/// * `start_at_bar` - The starting point for the calculation.
+/// * `degree` - The degree of the local polynomial.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{local_polynomial_regression, GaussianKernel, LocalPolynomial};
+/// let src = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// let result = local_polynomial_regression(&src, &GaussianKernel { look_back: 2.0 }, 2, LocalPolynomial::Linear).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn local_polynomial_regression<K: Kernel + ?Sized>(src: &Series, kernel: &K, start_at_bar: i32, degree: LocalPolynomial) -> Result<Series, Box<dyn std::error::Error>> {
+    if degree == LocalPolynomial::Constant {
+        return kernel_regression(src, kernel, start_at_bar);
+    }
+    if start_at_bar < 0 {
+        return Err("start_at_bar must not be negative".into());
+    }
+    let weights = kernel_weights(kernel, (start_at_bar + 2) as usize);
+    let values = kernel_values(src, NanPolicy::default())?;
+    let size = weights.len();
+    if values.len() < size {
+        return Err(format!("at least {} values are required by the kernel, got {}", size, values.len()).into());
+    }
+    let mut kernel_line = vec![None; values.len()];
+    for bar_index in size - 1..values.len() {
+        let window = values[bar_index + 1 - size..=bar_index].iter().rev();
+        kernel_line[bar_index] = local_intercept(window, &weights, degree.degree());
+    }
+    Ok(Series::new(src.name(), kernel_line))
+}
+
+/// Reads a timestamp column as seconds, nulls are `None`.
+fn timestamp_seconds(time: &Series) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    let scale = match time.dtype() {
+        DataType::Datetime(TimeUnit::Nanoseconds, _) => 1e-9,
+        DataType::Datetime(TimeUnit::Microseconds, _) => 1e-6,
+        DataType::Datetime(TimeUnit::Milliseconds, _) => 1e-3,
+        DataType::Date => 86400.0,
+        dtype => return Err(format!("{} must be a datetime or a date column, got {}", time.name(), dtype).into()),
+    };
+    let ticks = time.to_physical_repr().cast(&DataType::Int64)?;
+    Ok(ticks.i64()?.into_iter().map(|t| t.map(|t| t as f64 * scale)).collect())
+}
+
+/// Calculates the kernel regression of a series with irregular timestamps.
+///
+/// The window is the last `start_at_bar + 2` bars as in [`kernel_regression`], not a time span: a bar older
+/// than the bandwidth is still in the window with the weight of its age. The values are weighted by their
+/// time distance to the estimated bar instead of their bar offset, so session gaps and irregular bars weigh
+/// what their age is. The distances are in seconds and the kernel is built with the bandwidth in seconds
+/// as `look_back`, the kernel is evaluated with [`TimeKernel::weight_at`]. The period of the periodic kernels
+/// is a distance as well and must be given in seconds.
+/// Bars with a missing value or timestamp are skipped, the timestamps must not decrease.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `time` - The timestamps of the values, a datetime or a date column.
+/// * `bandwidth` - The bandwidth of the kernel.
+/// * `kernel` - Builds the kernel of the bandwidth in seconds.
+/// * `start_at_bar` - The starting point for the calculation.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use polars::prelude::*;
+/// use tech_analysis::{time_kernel_regression, GaussianKernel};
+/// let src = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+/// // a gap of an hour after the second bar
+/// let time = Series::new("time", vec![0i64, 60_000, 3_660_000, 3_720_000, 3_780_000])
+///     .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+///     .unwrap();
+/// let result = time_kernel_regression(&src, &time, Duration::from_secs(120), |look_back| GaussianKernel { look_back }, 2).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn time_kernel_regression<K, F>(src: &Series, time: &Series, bandwidth: Duration, kernel: F, start_at_bar: i32) -> Result<Series, Box<dyn std::error::Error>>
+where
+    K: TimeKernel,
+    F: Fn(f64) -> K,
+{
+    if start_at_bar < 0 {
+        return Err("start_at_bar must not be negative".into());
+    }
+    if bandwidth.is_zero() {
+        return Err("bandwidth must be bigger than zero".into());
+    }
+    let values = kernel_values(src, NanPolicy::default())?;
+    let seconds = timestamp_seconds(time)?;
+    if values.len() != seconds.len() {
+        return Err(format!("{} timestamps for {} values", seconds.len(), values.len()).into());
+    }
+    let ordered = seconds.iter().flatten().zip(seconds.iter().flatten().skip(1)).all(|(a, b)| a <= b);
+    if !ordered {
+        return Err(format!("the timestamps of {} must not decrease", time.name()).into());
+    }
+    let size = (start_at_bar + 2) as usize;
+    if values.len() < size {
+        return Err(format!("at least {} values are required by the kernel, got {}", size, values.len()).into());
+    }
+    let kernel = kernel(bandwidth.as_secs_f64());
+    let mut kernel_line = vec![None; values.len()];
+    for bar_index in size - 1..values.len() {
+        let Some(now) = seconds[bar_index] else { continue };
+        let (current_weight, cumulative_weight) = (bar_index + 1 - size..=bar_index)
+            .rev()
+            .filter_map(|i| values[i].zip(seconds[i]))
+            .map(|(y, t)| (y, kernel.weight_at(now - t)))
+            .fold((0.0, 0.0), |(c, w_sum), (y, w)| (c + y * w, w_sum + w));
+        kernel_line[bar_index] = if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) };
+    }
+    Ok(Series::new(src.name(), kernel_line))
+}
+
+/// Whether a kernel estimate may use the bars after the estimated bar.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Lookahead {
+    /// Only the current and past bars are used, the estimate of a bar never changes.
+    Causal,
+    /// The bars on both sides are used, the estimate of a bar changes as new bars arrive (repaints).
+    /// For offline labelling and research only, never for signals or backtests.
+    TwoSided,
+}
+
+/// Calculates the kernel estimate of a series over `window` bars on each side.
+///
+/// With `Lookahead::Causal` this is [`kernel_regression`] over the last `window + 1` values,
+/// the first `window` bars are null. With `Lookahead::TwoSided` the estimate of bar `t` is the
+/// average of the bars `t - window..=t + window` weighted by their distance to `t`, the weights
+/// are renormalized where the window is cut by the start or the end of the series or misses values.
+/// A two-sided estimate uses future bars and must never feed signals or backtests.
+///
+/// # Arguments
+///
+/// * `src` - The input series of values.
+/// * `kernel` - The kernel weighting the values by their distance.
+/// * `window` - The number of bars on each side.
+/// * `lookahead` - Whether the bars after the estimated bar are used.
+///
+/// # Returns
+///
+/// A new series containing the kernel estimates.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{kernel_smoothing, GaussianKernel, Lookahead};
+/// let src = Series::new("data", vec![1.0, 2.0, 3.0, 2.0, 1.0]);
+/// let result = kernel_smoothing(&src, &GaussianKernel { look_back: 1.0 }, 2, Lookahead::TwoSided).unwrap();
+/// println!("{:?}", result);
+/// ```
+pub fn kernel_smoothing<K: Kernel + ?Sized>(src: &Series, kernel: &K, window: usize, lookahead: Lookahead) -> Result<Series, Box<dyn std::error::Error>> {
+    if window == 0 {
+        return Err("window must be bigger than zero".into());
+    }
+    match lookahead {
+        Lookahead::Causal => kernel_regression(src, kernel, window as i32 - 1),
+        Lookahead::TwoSided => {
+            let values = kernel_values(src, NanPolicy::default())?;
+            let weights = kernel_weights(kernel, window + 1);
+            let n = values.len();
+            let smoothed: Vec<Option<f64>> = (0..n)
+                .map(|t| {
+                    let (mut current_weight, mut cumulative_weight) = (0.0, 0.0);
+                    for j in t.saturating_sub(window)..(t + window + 1).min(n) {
+                        if let Some(y) = values[j] {
+                            let w = weights[t.abs_diff(j)];
+                            current_weight += y * w;
+                            cumulative_weight += w;
+                        }
+                    }
+                    if cumulative_weight == 0.0 { None } else { Some(current_weight / cumulative_weight) }
+                })
+                .collect();
+            Ok(Series::new(src.name(), smoothed))
+        }
+    }
+}
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Boxcar(usize);
+
+    impl Kernel for Boxcar {
+        fn weight(&self, distance: usize) -> f64 {
+            if distance < self.0 { 1.0 } else { 0.0 }
+        }
+    }
+
+    #[test]
+    fn test_kernel_regression() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        // a boxcar kernel is a simple moving average
+        let result = kernel_regression(&src, &Boxcar(2), 2)?;
+        let values: Vec<Option<f64>> = result.f64()?.into_iter().collect();
+        assert_eq!(values, vec![None, None, None, Some(3.5), Some(4.5), Some(5.5)]);
+        // trait objects work as well
+        let kernel: Box<dyn Kernel> = Box::new(LaplaceKernel { look_back: 2.0 });
+        assert_eq!(kernel_regression(&src, kernel.as_ref(), 2)?.len(), 6);
+        assert!(kernel_regression(&src, &Boxcar(2), 5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_kernel_weights() {
+        // the epanechnikov kernel ignores the values beyond its bandwidth
+        let weights = kernel_weights(&EpanechnikovKernel { look_back: 4.0 }, 10);
+        assert!(weights[4..].iter().all(|w| *w == 0.0));
+        // the periodic kernel repeats every period
+        let weights = kernel_weights(&PeriodicKernel { look_back: 8.0, period: 6.0 }, 13);
+        assert!((weights[0] - weights[6]).abs() < 1e-12 && (weights[1] - weights[7]).abs() < 1e-12);
+        // every kernel weighs the current bar the most
+        let kernels: [&dyn Kernel; 4] = [
+            &RationalQuadraticKernel { look_back: 8.0, relative_weight: 8.0 },
+            &GaussianKernel { look_back: 8.0 },
+            &LocallyPeriodicKernel { look_back: 8.0, period: 6.0 },
+            &LaplaceKernel { look_back: 8.0 },
+        ];
+        for kernel in kernels {
+            assert!((1..20).all(|d| kernel.weight(d) <= kernel.weight(0)));
+        }
+    }
+
+    #[test]
+    fn test_kernel_smoothing() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("data", (0..30).map(|i| i as f64 * 2.0).collect::<Vec<f64>>());
+        let kernel = GaussianKernel { look_back: 3.0 };
+        // symmetric weights reproduce a line away from the edges
+        let smoothed = kernel_smoothing(&src, &kernel, 5, Lookahead::TwoSided)?;
+        let smoothed: Vec<f64> = smoothed.f64()?.into_no_null_iter().collect();
+        assert!((5..25).all(|t| (smoothed[t] - t as f64 * 2.0).abs() < 1e-9));
+        // the edges only see one side
+        assert!(smoothed[0] > 0.0 && smoothed[29] < 58.0);
+        // the causal mode is the kernel regression
+        let causal = kernel_smoothing(&src, &kernel, 5, Lookahead::Causal)?;
+        assert!(causal.series_equal_missing(&kernel_regression(&src, &kernel, 4)?));
+        assert!(kernel_smoothing(&src, &kernel, 0, Lookahead::TwoSided).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_two_sided_repaints() -> Result<(), Box<dyn std::error::Error>> {
+        let kernel = LaplaceKernel { look_back: 2.0 };
+        let history = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        let extended = Series::new("data", vec![1.0, 2.0, 3.0, 4.0, 5.0, 0.0]);
+        let before = kernel_smoothing(&history, &kernel, 2, Lookahead::TwoSided)?;
+        let after = kernel_smoothing(&extended, &kernel, 2, Lookahead::TwoSided)?;
+        assert_ne!(before.f64()?.get(4), after.f64()?.get(4));
+        let before = kernel_smoothing(&history, &kernel, 2, Lookahead::Causal)?;
+        let after = kernel_smoothing(&extended, &kernel, 2, Lookahead::Causal)?;
+        assert_eq!(before.f64()?.get(4), after.f64()?.get(4));
+        Ok(())
+    }
+
+    #[test]
+    fn test_nan_policy() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("data", vec![Some(1.0), Some(2.0), None, Some(f64::NAN), Some(5.0), Some(6.0)]);
+        let estimate = |policy: NanPolicy| -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+            Ok(kernel_regression_with_policy(&src, &Boxcar(2), 0, policy)?.f64()?.into_iter().collect())
+        };
+        // nulls are always skipped
+        let skip = estimate(NanPolicy::Skip)?;
+        assert_eq!(skip, vec![None, Some(1.5), Some(2.0), None, Some(5.0), Some(5.5)]);
+        let propagate = estimate(NanPolicy::Propagate)?;
+        assert!(propagate[3].unwrap().is_nan() && propagate[4].unwrap().is_nan());
+        assert_eq!(propagate[2], Some(2.0));
+        assert!(estimate(NanPolicy::Error).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_kernel_regression() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("data", (0..60).map(|i| (i as f64 / 4.0).sin()).collect::<Vec<f64>>());
+        let minutes = |times: Vec<i64>| Series::new("time", times.iter().map(|t| t * 60_000).collect::<Vec<i64>>()).cast(&DataType::Datetime(TimeUnit::Milliseconds, None));
+        // evenly spaced bars are the bar offset regression
+        let regular = minutes((0..60).collect())?;
+        fn same<K: TimeKernel, F: Fn(f64) -> K>(src: &Series, time: &Series, time_kernel: F, bar_kernel: K) -> Result<(), Box<dyn std::error::Error>> {
+            let by_time = time_kernel_regression(src, time, Duration::from_secs(8 * 60), time_kernel, 25)?;
+            let by_bar = kernel_regression(src, &bar_kernel, 25)?;
+            assert_eq!(by_time.null_count(), 26);
+            for (a, b) in by_time.f64()?.into_iter().zip(by_bar.f64()?).skip(26) {
+                assert!((a.unwrap() - b.unwrap()).abs() < 1e-9);
+            }
+            Ok(())
+        }
+        same(&src, &regular, |look_back| GaussianKernel { look_back }, GaussianKernel { look_back: 8.0 })?;
+        same(&src, &regular, |look_back| RationalQuadraticKernel { look_back, relative_weight: 8.0 }, RationalQuadraticKernel { look_back: 8.0, relative_weight: 8.0 })?;
+        // the periodic kernels take their period in seconds, their look_back is no distance
+        same(&src, &regular, |_| PeriodicKernel { look_back: 8.0, period: 12.0 * 60.0 }, PeriodicKernel { look_back: 8.0, period: 12.0 })?;
+        // the bars before a session gap weigh less than their bar offset says
+        let gap = minutes((0..60).map(|i| if i < 40 { i } else { i + 600 }).collect())?;
+        let gaussian = |look_back| GaussianKernel { look_back };
+        let by_time = time_kernel_regression(&src, &gap, Duration::from_secs(8 * 60), gaussian, 25)?;
+        let by_bar = kernel_regression(&src, &GaussianKernel { look_back: 8.0 }, 25)?;
+        let last = |s: &Series| s.f64().unwrap().get(42).unwrap();
+        let recent = (40..=42).map(|i| src.f64().unwrap().get(i).unwrap()).sum::<f64>() / 3.0;
+        assert!((last(&by_time) - recent).abs() < (last(&by_bar) - recent).abs());
+        // dates and missing timestamps
+        let days = Series::new("date", (0..60).collect::<Vec<i32>>()).cast(&DataType::Date)?;
+        let daily = time_kernel_regression(&src, &days, Duration::from_secs(8 * 86400), gaussian, 25)?;
+        assert!((last(&daily) - last(&kernel_regression(&src, &GaussianKernel { look_back: 8.0 }, 25)?)).abs() < 1e-9);
+        let missing = Series::new("time", (0..60).map(|i| if i == 42 { None } else { Some(i * 60_000) }).collect::<Vec<Option<i64>>>())
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+        assert_eq!(time_kernel_regression(&src, &missing, Duration::from_secs(480), gaussian, 25)?.f64()?.get(42), None);
+        // unordered, wrong dtype and zero bandwidth
+        assert!(time_kernel_regression(&src, &minutes((0..60).rev().collect())?, Duration::from_secs(480), gaussian, 25).is_err());
+        assert!(time_kernel_regression(&src, &src, Duration::from_secs(480), gaussian, 25).is_err());
+        assert!(time_kernel_regression(&src, &regular, Duration::ZERO, gaussian, 25).is_err());
+        Ok(())
+    }
+}
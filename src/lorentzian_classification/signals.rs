@@ -0,0 +1,296 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/17 15:08
+ * @Email: uyplayer@qq.com
+ * @File: signals
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! kernel regression signals, rates of change, crossovers and colour changes
+
+use polars::prelude::*;
+use crate::lorentzian_classification::{helper, kernel};
+use crate::lorentzian_classification::adaptive::adaptive_values;
+use crate::lorentzian_classification::regression::{GaussianKernel, RationalQuadraticKernel};
+use crate::lorentzian_classification::types::{ConfigCheck, KernelFilter};
+
+
+/// The kernel signals of every bar, same names as in the tradingview script.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct KernelSignals {
+    pub yhat1: Vec<f64>,
+    pub yhat2: Vec<f64>,
+    pub is_bullish_rate: Vec<bool>,
+    pub is_bearish_rate: Vec<bool>,
+    pub is_bullish_change: Vec<bool>,
+    pub is_bearish_change: Vec<bool>,
+    pub is_bullish_cross: Vec<bool>,
+    pub is_bearish_cross: Vec<bool>,
+    pub is_bullish_smooth: Vec<bool>,
+    pub is_bearish_smooth: Vec<bool>,
+    pub is_bullish_color_change: Vec<bool>,
+    pub is_bearish_color_change: Vec<bool>,
+    pub alert_bullish: Vec<bool>,
+    pub alert_bearish: Vec<bool>,
+    pub is_bullish: Vec<bool>,
+    pub is_bearish: Vec<bool>,
+}
+
+impl KernelFilter {
+    /// Calculates the kernel signals of the source values, `ohlc` holds the high, low and close values
+    /// an ATR adaptive bandwidth needs.
+    pub(crate) fn signal_values(&self, src: &[f64], ohlc: Option<(&[f64], &[f64], &[f64])>) -> Result<KernelSignals, Box<dyn std::error::Error>> {
+        let start_at_bar = self.regression_level as i32;
+        if src.len() < (start_at_bar + 2) as usize {
+            return Err(format!("at least {} bars are required by the kernel regression", start_at_bar + 2).into());
+        }
+        if self.look_back_window - self.crossover_lag <= 0 {
+            // the gaussian line uses `look_back_window - crossover_lag` as its bandwidth
+            return Err(format!("crossover_lag {} must be smaller than look_back_window {}", self.crossover_lag, self.look_back_window).into());
+        }
+        let (yhat1, yhat2) = match &self.adaptive_bandwidth {
+            None => {
+                let source = Series::new("data", src);
+                (
+                    helper::series_values(&kernel::rational_quadratic(&source, self.look_back_window, self.relative_weight, start_at_bar)?)?,
+                    helper::series_values(&kernel::gaussian(&source, self.look_back_window - self.crossover_lag, start_at_bar)?)?,
+                )
+            }
+            Some(bandwidth) => {
+                // both lines follow the same volatility ratio
+                let ratio = bandwidth.volatility_ratio(src, ohlc)?;
+                let values: Vec<Option<f64>> = src.iter().map(|v| Some(*v).filter(|v| !v.is_nan())).collect();
+                let size = (start_at_bar + 2) as usize;
+                let relative_weight = self.relative_weight as f64;
+                let look_back = bandwidth.look_back_values(self.look_back_window as f64, &ratio);
+                let yhat1 = adaptive_values(&values, &look_back, |look_back| RationalQuadraticKernel { look_back, relative_weight }, size)?;
+                let look_back = bandwidth.look_back_values((self.look_back_window - self.crossover_lag) as f64, &ratio);
+                let yhat2 = adaptive_values(&values, &look_back, |look_back| GaussianKernel { look_back }, size)?;
+                let to_values = |line: Vec<Option<f64>>| -> Vec<f64> { line.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect() };
+                (to_values(yhat1), to_values(yhat2))
+            }
+        };
+        let n = src.len();
+
+        let is_bullish_rate: Vec<bool> = (0..n).map(|i| i >= 1 && yhat1[i - 1] < yhat1[i]).collect();
+        let is_bearish_rate: Vec<bool> = (0..n).map(|i| i >= 1 && yhat1[i - 1] > yhat1[i]).collect();
+        let is_bullish_change: Vec<bool> = (0..n).map(|i| i >= 1 && is_bullish_rate[i] && is_bearish_rate[i - 1]).collect();
+        let is_bearish_change: Vec<bool> = (0..n).map(|i| i >= 1 && is_bearish_rate[i] && is_bullish_rate[i - 1]).collect();
+        let is_bullish_cross: Vec<bool> = (0..n).map(|i| i >= 1 && yhat2[i] > yhat1[i] && yhat2[i - 1] <= yhat1[i - 1]).collect();
+        let is_bearish_cross: Vec<bool> = (0..n).map(|i| i >= 1 && yhat2[i] < yhat1[i] && yhat2[i - 1] >= yhat1[i - 1]).collect();
+        let is_bullish_smooth: Vec<bool> = (0..n).map(|i| yhat2[i] >= yhat1[i]).collect();
+        let is_bearish_smooth: Vec<bool> = (0..n).map(|i| yhat2[i] <= yhat1[i]).collect();
+
+        // the line is green when bullish and red otherwise
+        let is_green = if self.use_kernel_smoothing { &is_bullish_smooth } else { &is_bullish_rate };
+        let is_bullish_color_change: Vec<bool> = (0..n).map(|i| i >= 1 && is_green[i] && !is_green[i - 1]).collect();
+        let is_bearish_color_change: Vec<bool> = (0..n).map(|i| i >= 1 && !is_green[i] && is_green[i - 1]).collect();
+
+        let (alert_bullish, alert_bearish) = if self.use_kernel_smoothing {
+            (is_bullish_cross.clone(), is_bearish_cross.clone())
+        } else {
+            (is_bullish_change.clone(), is_bearish_change.clone())
+        };
+        let is_bullish: Vec<bool> = (0..n)
+            .map(|i| !self.use_kernel_filter || if self.use_kernel_smoothing { is_bullish_smooth[i] } else { is_bullish_rate[i] })
+            .collect();
+        let is_bearish: Vec<bool> = (0..n)
+            .map(|i| !self.use_kernel_filter || if self.use_kernel_smoothing { is_bearish_smooth[i] } else { is_bearish_rate[i] })
+            .collect();
+
+        Ok(KernelSignals {
+            yhat1,
+            yhat2,
+            is_bullish_rate,
+            is_bearish_rate,
+            is_bullish_change,
+            is_bearish_change,
+            is_bullish_cross,
+            is_bearish_cross,
+            is_bullish_smooth,
+            is_bearish_smooth,
+            is_bullish_color_change,
+            is_bearish_color_change,
+            alert_bullish,
+            alert_bearish,
+            is_bullish,
+            is_bearish,
+        })
+    }
+}
+
+/// Calculates the kernel regression signals of the tradingview script.
+///
+/// `yhat1` is the `rational_quadratic` estimate with `look_back_window`, `relative_weight` and
+/// `regression_level`, `yhat2` is the `gaussian` estimate lagged by `crossover_lag`.
+/// Only the source is known here, so an `adaptive_bandwidth` must measure the volatility with
+/// `VolatilityMeasure::Stdev`, the ATR needs the high, low and close columns of `lorentzian_classification`.
+///
+/// # Arguments
+///
+/// * `src` - The source series, usually close.
+/// * `kernel` - The kernel filter settings.
+///
+/// # Returns
+///
+/// A DataFrame with the columns
+/// * `yhat1`, `yhat2` - the kernel estimates, null during the warm up
+/// * `is_bullish_rate`, `is_bearish_rate` - yhat1 rising / falling
+/// * `is_bullish_change`, `is_bearish_change` - yhat1 turning up / down
+/// * `is_bullish_cross`, `is_bearish_cross` - yhat2 crossing over / under yhat1
+/// * `is_bullish_smooth`, `is_bearish_smooth` - yhat2 above / below yhat1
+/// * `is_bullish_color_change`, `is_bearish_color_change` - the kernel line turning green / red
+/// * `alert_bullish`, `alert_bearish` - the kernel alerts, crosses with smoothing and changes without
+/// * `is_bullish`, `is_bearish` - the kernel filter of the entries, always true when `use_kernel_filter` is off
+///
+/// An error when `crossover_lag` is not smaller than `look_back_window`.
+///
+/// # Example
+///
+/// ```rust
+/// use polars::prelude::*;
+/// use tech_analysis::{kernel_signals, KernelFilter};
+/// let src = Series::new("close", (0..60).map(|i| (i as f64 / 6.0).sin()).collect::<Vec<f64>>());
+/// let kernel = KernelFilter { use_kernel_filter: true, show_kernel_estimate: true, use_kernel_smoothing: false,
+///     look_back_window: 8, relative_weight: 8.0, regression_level: 25.0, crossover_lag: 2, adaptive_bandwidth: None };
+/// let signals = kernel_signals(&src, &kernel).unwrap();
+/// println!("{:?}", signals);
+/// ```
+pub fn kernel_signals(src: &Series, kernel: &KernelFilter) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    kernel.configuration_check();
+    let signals = kernel.signal_values(&helper::series_values(src)?, None)?;
+    Ok(DataFrame::new(vec![
+        Series::new("yhat1", helper::nan_to_null(&signals.yhat1)),
+        Series::new("yhat2", helper::nan_to_null(&signals.yhat2)),
+        Series::new("is_bullish_rate", signals.is_bullish_rate),
+        Series::new("is_bearish_rate", signals.is_bearish_rate),
+        Series::new("is_bullish_change", signals.is_bullish_change),
+        Series::new("is_bearish_change", signals.is_bearish_change),
+        Series::new("is_bullish_cross", signals.is_bullish_cross),
+        Series::new("is_bearish_cross", signals.is_bearish_cross),
+        Series::new("is_bullish_smooth", signals.is_bullish_smooth),
+        Series::new("is_bearish_smooth", signals.is_bearish_smooth),
+        Series::new("is_bullish_color_change", signals.is_bullish_color_change),
+        Series::new("is_bearish_color_change", signals.is_bearish_color_change),
+        Series::new("alert_bullish", signals.alert_bullish),
+        Series::new("alert_bearish", signals.alert_bearish),
+        Series::new("is_bullish", signals.is_bullish),
+        Series::new("is_bearish", signals.is_bearish),
+    ])?)
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::adaptive::{AdaptiveBandwidth, VolatilityMeasure};
+
+    fn kernel_filter() -> KernelFilter {
+        KernelFilter {
+            use_kernel_filter: true,
+            show_kernel_estimate: true,
+            use_kernel_smoothing: false,
+            look_back_window: 8,
+            relative_weight: 8.0,
+            regression_level: 25.0,
+            crossover_lag: 2,
+            adaptive_bandwidth: None,
+        }
+    }
+
+    fn flags(df: &DataFrame, name: &str) -> Vec<bool> {
+        df.column(name).unwrap().bool().unwrap().into_no_null_iter().collect()
+    }
+
+    #[test]
+    fn test_kernel_signals() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("close", (0..300).map(|i| 100.0 + (i as f64 / 20.0).sin() * 10.0).collect::<Vec<f64>>());
+        let signals = kernel_signals(&src, &kernel_filter())?;
+        assert_eq!(signals.height(), 300);
+        let bullish_rate = flags(&signals, "is_bullish_rate");
+        let bullish_change = flags(&signals, "is_bullish_change");
+        let bearish_change = flags(&signals, "is_bearish_change");
+        // a sine wave turns up and down once per period
+        assert!(bullish_change.iter().filter(|c| **c).count() >= 2);
+        assert!(bearish_change.iter().filter(|c| **c).count() >= 2);
+        for i in 1..300 {
+            if bullish_change[i] {
+                assert!(bullish_rate[i] && !bullish_rate[i - 1]);
+            }
+        }
+        // without smoothing the alerts are the rate changes
+        assert_eq!(flags(&signals, "alert_bullish"), bullish_change);
+        Ok(())
+    }
+
+    #[test]
+    fn test_kernel_crossovers() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("close", (0..300).map(|i| 100.0 + (i as f64 / 20.0).sin() * 10.0).collect::<Vec<f64>>());
+        let kernel = KernelFilter { use_kernel_smoothing: true, ..kernel_filter() };
+        let signals = kernel_signals(&src, &kernel)?;
+        let smooth = flags(&signals, "is_bullish_smooth");
+        let cross = flags(&signals, "is_bullish_cross");
+        let crossunder = flags(&signals, "is_bearish_cross");
+        assert!(cross.iter().any(|c| *c) && crossunder.iter().any(|c| *c));
+        for i in 1..300 {
+            if cross[i] {
+                assert!(smooth[i]);
+            }
+        }
+        assert_eq!(flags(&signals, "alert_bearish"), crossunder);
+        assert_eq!(flags(&signals, "is_bullish"), smooth);
+        Ok(())
+    }
+
+    #[test]
+    fn test_kernel_filter_disabled() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("close", (0..100).map(|i| i as f64).collect::<Vec<f64>>());
+        let kernel = KernelFilter { use_kernel_filter: false, ..kernel_filter() };
+        let signals = kernel_signals(&src, &kernel)?;
+        assert!(flags(&signals, "is_bullish").iter().all(|b| *b));
+        assert!(flags(&signals, "is_bearish").iter().all(|b| *b));
+        assert!(kernel_signals(&src.slice(0, 10), &kernel).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_crossover_lag() -> Result<(), Box<dyn std::error::Error>> {
+        // the gaussian line needs a positive bandwidth `look_back_window - crossover_lag`
+        let src = Series::new("close", (0..100).map(|i| i as f64).collect::<Vec<f64>>());
+        for crossover_lag in [8, 9] {
+            let kernel = KernelFilter { crossover_lag, ..kernel_filter() };
+            assert!(kernel_signals(&src, &kernel).is_err());
+        }
+        kernel_signals(&src, &KernelFilter { crossover_lag: 7, ..kernel_filter() })?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptive_bandwidth() -> Result<(), Box<dyn std::error::Error>> {
+        let src = Series::new("close", (0..200).map(|i| (i as f64 / 8.0).sin() * (1.0 + i as f64 / 20.0)).collect::<Vec<f64>>());
+        // bounds pinned to the fixed bandwidths give the fixed lines once the volatility is known
+        let pinned = AdaptiveBandwidth { measure: VolatilityMeasure::Stdev, min_look_back: 8.0, max_look_back: 8.0, ..Default::default() };
+        let fixed = kernel_signals(&src, &kernel_filter())?;
+        let adaptive = kernel_signals(&src, &KernelFilter { adaptive_bandwidth: Some(pinned), crossover_lag: 0, ..kernel_filter() })?;
+        let expected = kernel_signals(&src, &KernelFilter { crossover_lag: 0, ..kernel_filter() })?;
+        for name in ["yhat1", "yhat2"] {
+            let (a, b) = (adaptive.column(name)?.f64()?, expected.column(name)?.f64()?);
+            assert_eq!(a.null_count(), 49);
+            for (a, b) in a.into_iter().zip(b).skip(49) {
+                assert!((a.unwrap() - b.unwrap()).abs() <= 1e-9);
+            }
+        }
+        let bandwidth = AdaptiveBandwidth { measure: VolatilityMeasure::Stdev, ..Default::default() };
+        let adaptive = kernel_signals(&src, &KernelFilter { adaptive_bandwidth: Some(bandwidth), ..kernel_filter() })?;
+        assert_eq!(adaptive.height(), fixed.height());
+        assert!(!adaptive.column("yhat1")?.series_equal_missing(fixed.column("yhat1")?));
+        // the atr needs the high and low values
+        assert!(kernel_signals(&src, &KernelFilter { adaptive_bandwidth: Some(AdaptiveBandwidth::default()), ..kernel_filter() }).is_err());
+        Ok(())
+    }
+}
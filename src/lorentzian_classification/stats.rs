@@ -0,0 +1,290 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/18 09:26
+ * @Email: uyplayer@qq.com
+ * @File: stats
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! trade statistics of lorentzian classification, same as the trade stats table of tradingview
+
+use std::fmt;
+use std::ops::Range;
+use polars::prelude::*;
+use crate::lorentzian_classification::helper;
+use crate::lorentzian_classification::types::{ConfigCheck, Settings};
+
+
+/// The trade statistics of the tradingview script.
+///
+/// Counted as in pine:
+/// * a trade is priced at `src` in the worst case, and at `(high + low + open + open) / 4` otherwise
+/// * a trade is a win when its exit price is better than its entry price and a loss when it is worse,
+///   trades closed at their entry price are neither
+/// * `total_trades` is `wins + losses`
+/// * an early signal flip is an entry at a bar where the signal changed while it also changed in the 3 bars before
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeStats {
+    /// The number of won and lost trades.
+    pub total_trades: usize,
+    /// The number of won trades.
+    pub wins: usize,
+    /// The number of lost trades.
+    pub losses: usize,
+    /// The number of entries on an early signal flip.
+    pub early_signal_flips: usize,
+    /// `wins / (wins + losses)`, NaN without trades.
+    pub win_rate: f64,
+    /// `wins / losses`, NaN without losses.
+    pub win_loss_ratio: f64,
+}
+
+impl TradeStats {
+    /// Calculates the statistics over the bars predicted by the classifier, the last `settings.max_bars_back` bars.
+    ///
+    /// # Arguments
+    ///
+    /// * `df` - The output of [`crate::lorentzian_classification`], with the `open`, `high`, `low`, source,
+    ///   `signal`, `start_long`, `end_long`, `start_short` and `end_short` columns.
+    /// * `settings` - The settings of the classification.
+    /// * `use_worst_case` - Whether trades are priced at the source instead of the bar average.
+    ///
+    /// # Returns
+    ///
+    /// The statistics of the bars.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use polars::prelude::*;
+    /// use tech_analysis::{Settings, TradeStats, DEFAULT_FEATURES};
+    /// let settings = Settings { source: "close", neighbors_count: 8, max_bars_back: 2000, feature_count: 5,
+    ///     features: DEFAULT_FEATURES, show_exits: false, use_dynamic_exits: false, use_ema_filter: false,
+    ///     ema_period: 200, use_sma_filter: false, sma_period: 200 };
+    /// let df = df!(
+    ///     "open" => [1.0, 1.0, 2.0, 3.0],
+    ///     "high" => [1.0, 1.0, 2.0, 3.0],
+    ///     "low" => [1.0, 1.0, 2.0, 3.0],
+    ///     "close" => [1.0, 1.0, 2.0, 3.0],
+    ///     "signal" => [0, 1, 1, 1],
+    ///     "start_long" => [false, true, false, false],
+    ///     "end_long" => [false, false, false, true],
+    ///     "start_short" => [false, false, false, false],
+    ///     "end_short" => [false, false, false, false]
+    /// ).unwrap();
+    /// let stats = TradeStats::new(&df, &settings, true).unwrap();
+    /// assert_eq!(stats.wins, 1);
+    /// println!("{}", stats);
+    /// ```
+    pub fn new(df: &DataFrame, settings: &Settings, use_worst_case: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        let last_bar_index = df.height().saturating_sub(1);
+        let max_bars_back_index = last_bar_index.saturating_sub(settings.max_bars_back as usize);
+        TradeStats::range(df, settings, use_worst_case, max_bars_back_index..df.height())
+    }
+
+    /// Calculates the statistics over a range of bars.
+    ///
+    /// Trades opened before the range are ignored, the signal history before the range is still
+    /// used to find the early signal flips.
+    ///
+    /// # Arguments
+    ///
+    /// * `df` - The output of [`crate::lorentzian_classification`], see [`TradeStats::new`].
+    /// * `settings` - The settings of the classification.
+    /// * `use_worst_case` - Whether trades are priced at the source instead of the bar average.
+    /// * `bars` - The bar indices to count.
+    ///
+    /// # Returns
+    ///
+    /// The statistics of the bars.
+    pub fn range(df: &DataFrame, settings: &Settings, use_worst_case: bool, bars: Range<usize>) -> Result<Self, Box<dyn std::error::Error>> {
+        settings.configuration_check();
+        if bars.end > df.height() || bars.start > bars.end {
+            return Err(format!("bars {:?} are out of the {} rows", bars, df.height()).into());
+        }
+        let market_price: Vec<f64> = if use_worst_case {
+            helper::column_values(df, settings.source)?
+        } else {
+            let open = helper::column_values(df, "open")?;
+            let high = helper::column_values(df, "high")?;
+            let low = helper::column_values(df, "low")?;
+            (0..df.height()).map(|i| (high[i] + low[i] + open[i] + open[i]) / 4.0).collect()
+        };
+        let signal: Vec<f64> = helper::column_values(df, "signal")?;
+        let start_long = flag_values(df, "start_long")?;
+        let end_long = flag_values(df, "end_long")?;
+        let start_short = flag_values(df, "start_short")?;
+        let end_short = flag_values(df, "end_short")?;
+
+        let changed = |i: usize| i >= 1 && signal[i] != signal[i - 1];
+        let is_early_signal_flip = |i: usize| changed(i) && (1..=3).any(|lag| i >= lag && changed(i - lag));
+
+        let mut long_price: Option<f64> = None;
+        let mut short_price: Option<f64> = None;
+        let (mut wins, mut losses, mut early_signal_flips) = (0, 0, 0);
+        for i in bars {
+            let mut count = |delta: Option<f64>| match delta {
+                Some(delta) if delta > 0.0 => wins += 1,
+                Some(delta) if delta < 0.0 => losses += 1,
+                _ => {}
+            };
+            if start_long[i] {
+                long_price = Some(market_price[i]);
+            }
+            if end_long[i] {
+                count(long_price.map(|price| market_price[i] - price));
+            }
+            if start_short[i] {
+                short_price = Some(market_price[i]);
+            }
+            if end_short[i] {
+                count(short_price.map(|price| price - market_price[i]));
+            }
+            if (start_long[i] || start_short[i]) && is_early_signal_flip(i) {
+                early_signal_flips += 1;
+            }
+        }
+
+        let ratio = |a: usize, b: usize| if b == 0 { f64::NAN } else { a as f64 / b as f64 };
+        Ok(TradeStats {
+            total_trades: wins + losses,
+            wins,
+            losses,
+            early_signal_flips,
+            win_rate: ratio(wins, wins + losses),
+            win_loss_ratio: ratio(wins, losses),
+        })
+    }
+}
+
+impl fmt::Display for TradeStats {
+    /// Formats the statistics as the trade stats table of tradingview.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Trade Stats")?;
+        writeln!(f, "Winrate: {:.1}%", self.win_rate * 100.0)?;
+        writeln!(f, "Trades: {} ({}|{})", self.total_trades, self.wins, self.losses)?;
+        writeln!(f, "WL Ratio: {:.2}", self.win_loss_ratio)?;
+        write!(f, "Early Signal Flips: {}", self.early_signal_flips)
+    }
+}
+
+/// The values of a boolean column, nulls are false.
+fn flag_values(df: &DataFrame, name: &str) -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+    Ok(df.column(name)?.bool()?.into_iter().map(|v| v.unwrap_or(false)).collect())
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use crate::lorentzian_classification::classification::lorentzian_classification;
+    use crate::lorentzian_classification::types::{Filters, KernelFilter, DEFAULT_FEATURES};
+
+    fn settings() -> Settings<'static> {
+        Settings {
+            source: "close",
+            neighbors_count: 8,
+            max_bars_back: 2000,
+            feature_count: 5,
+            features: DEFAULT_FEATURES,
+            show_exits: false,
+            use_dynamic_exits: false,
+            use_ema_filter: false,
+            ema_period: 200,
+            use_sma_filter: false,
+            sma_period: 200,
+        }
+    }
+
+    fn classification(use_dynamic_exits: bool) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        let filters = Filters { use_volatility_filter: true, use_regime_filter: true, use_adx_filter: false, regime_threshold: -0.1, adx_threshold: 20 };
+        let kernel = KernelFilter {
+            use_kernel_filter: true,
+            show_kernel_estimate: true,
+            use_kernel_smoothing: false,
+            look_back_window: 8,
+            relative_weight: 8.0,
+            regression_level: 25.0,
+            crossover_lag: 2,
+            adaptive_bandwidth: None,
+        };
+        lorentzian_classification(&example()?, &Settings { use_dynamic_exits, ..settings() }, &filters, &kernel)
+    }
+
+    fn trades() -> PolarsResult<DataFrame> {
+        // the signal changes at bars 1, 3, 4, 5 and 8, the changes at 3, 4, 5 and 8 are early flips,
+        // only the ones at 5 and 8 open a trade
+        df!(
+            "open" => [10.0, 10.0, 11.0, 12.0, 12.0, 11.0, 13.0, 12.0, 12.0, 14.0],
+            "high" => [10.0, 10.0, 11.0, 12.0, 12.0, 11.0, 13.0, 12.0, 12.0, 14.0],
+            "low" => [10.0, 10.0, 11.0, 12.0, 12.0, 11.0, 13.0, 12.0, 12.0, 14.0],
+            "close" => [10.0, 10.0, 11.0, 12.0, 12.0, 11.0, 13.0, 12.0, 12.0, 14.0],
+            "signal" => [0, 1, 1, -1, 1, -1, -1, -1, 1, 1],
+            "start_long" => [false, true, false, false, false, false, false, false, true, false],
+            "end_long" => [false, false, false, true, false, false, false, false, false, true],
+            "start_short" => [false, false, false, false, false, true, false, false, false, false],
+            "end_short" => [false, false, false, false, false, false, false, true, false, false]
+        )
+    }
+
+    #[test]
+    fn test_trade_stats() -> Result<(), Box<dyn std::error::Error>> {
+        let stats = TradeStats::new(&trades()?, &settings(), true)?;
+        // long 10 -> 12 wins, short 11 -> 12 loses, long 12 -> 14 wins
+        assert_eq!(stats.wins, 2);
+        assert_eq!(stats.losses, 1);
+        assert_eq!(stats.total_trades, 3);
+        assert_eq!(stats.win_rate, 2.0 / 3.0);
+        assert_eq!(stats.win_loss_ratio, 2.0);
+        // the flips at bars 3 and 4 open no trade, the exit at bar 3 on a flip is not counted
+        assert_eq!(stats.early_signal_flips, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trade_stats_range() -> Result<(), Box<dyn std::error::Error>> {
+        let df = trades()?;
+        // the short opened at bar 5 is closed in the second range
+        let first = TradeStats::range(&df, &settings(), true, 0..6)?;
+        assert_eq!((first.wins, first.losses, first.total_trades, first.early_signal_flips), (1, 0, 1, 1));
+        // its exit at bar 7 has no entry in the range and is ignored,
+        // the flip at bar 5 before the range is still seen by the entry at bar 8
+        let second = TradeStats::range(&df, &settings(), true, 6..10)?;
+        assert_eq!((second.wins, second.losses, second.total_trades, second.early_signal_flips), (1, 0, 1, 1));
+        let empty = TradeStats::range(&df, &settings(), true, 4..4)?;
+        assert_eq!((empty.total_trades, empty.early_signal_flips), (0, 0));
+        assert!(empty.win_rate.is_nan());
+        assert!(TradeStats::range(&df, &settings(), true, 6..11).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_classification_trade_stats() -> Result<(), Box<dyn std::error::Error>> {
+        for use_dynamic_exits in [false, true] {
+            let df = classification(use_dynamic_exits)?;
+            let settings = Settings { use_dynamic_exits, ..settings() };
+            let stats = TradeStats::new(&df, &settings, false)?;
+            let ends = flag_values(&df, "end_long")?.iter().chain(flag_values(&df, "end_short")?.iter()).filter(|e| **e).count();
+            assert_eq!(stats.total_trades, stats.wins + stats.losses);
+            assert!(stats.total_trades > 0 && stats.total_trades <= ends);
+            eprintln!("{}", stats);
+            // the early signal flips are the entries on a signal change after another change in the 3 bars before
+            let signal = helper::column_values(&df, "signal")?;
+            let (start_long, start_short) = (flag_values(&df, "start_long")?, flag_values(&df, "start_short")?);
+            let changed = |i: usize| signal[i] != signal[i - 1];
+            let start = df.height() - 1 - settings.max_bars_back as usize;
+            let flips = (start..df.height())
+                .filter(|&i| (start_long[i] || start_short[i]) && changed(i) && (1..=3).any(|lag| changed(i - lag)))
+                .count();
+            assert!(flips > 0);
+            assert_eq!(stats.early_signal_flips, flips);
+        }
+        Ok(())
+    }
+}
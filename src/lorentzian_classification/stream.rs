@@ -0,0 +1,177 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/21 14:45
+ * @Email: uyplayer@qq.com
+ * @File: stream
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! incremental kernel estimators for live bars
+
+use std::collections::VecDeque;
+use crate::lorentzian_classification::regression::{kernel_weights, window_average, GaussianKernel, Kernel, RationalQuadraticKernel};
+
+
+/// Incremental kernel regression, one bar at a time.
+///
+/// Keeps a ring buffer of the last `start_at_bar + 2` values, every update costs O(window).
+/// Once the window is full, the estimates are the same as [`crate::kernel_regression`] bar for bar,
+/// `NaN` values are skipped as in the batch functions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelStream<K: Kernel> {
+    kernel: K,
+    weights: Vec<f64>,
+    cumulative_weight: f64,
+    window: VecDeque<Option<f64>>,
+    missing: usize,
+}
+
+impl<K: Kernel> KernelStream<K> {
+    /// Creates an empty stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `kernel` - The kernel weighting the values by their distance.
+    /// * `start_at_bar` - The starting point for the calculation, the window holds `start_at_bar + 2` values.
+    pub fn with_kernel(kernel: K, start_at_bar: i32) -> Self {
+        if start_at_bar < 0 {
+            panic!("start_at_bar must not be negative");
+        }
+        let weights = kernel_weights(&kernel, (start_at_bar + 2) as usize);
+        let cumulative_weight = weights.iter().sum();
+        KernelStream {
+            window: VecDeque::with_capacity(weights.len()),
+            missing: 0,
+            kernel,
+            weights,
+            cumulative_weight,
+        }
+    }
+
+    /// The kernel of the stream.
+    pub fn kernel(&self) -> &K {
+        &self.kernel
+    }
+
+    /// Adds the value of a new bar.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value of the new bar.
+    ///
+    /// # Returns
+    ///
+    /// The estimate of the bar, `None` until the window is full or when the window has no value.
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        if self.window.len() == self.weights.len() {
+            self.missing -= self.window.pop_front().flatten().is_none() as usize;
+        }
+        let value = Some(value).filter(|v| !v.is_nan());
+        self.missing += value.is_none() as usize;
+        self.window.push_back(value);
+        if self.window.len() < self.weights.len() {
+            return None;
+        }
+        window_average(self.window.iter().rev(), &self.weights, self.cumulative_weight, self.missing == 0)
+    }
+
+    /// Clears the window.
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.missing = 0;
+    }
+}
+
+/// Incremental [`crate::rational_quadratic`].
+pub type RationalQuadraticStream = KernelStream<RationalQuadraticKernel>;
+
+/// Incremental [`crate::gaussian`].
+pub type GaussianStream = KernelStream<GaussianKernel>;
+
+impl KernelStream<RationalQuadraticKernel> {
+    /// Creates an empty stream with the parameters of [`crate::rational_quadratic`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tech_analysis::RationalQuadraticStream;
+    /// let mut stream = RationalQuadraticStream::new(2, 3.0, 1);
+    /// for value in [1.0, 2.0, 3.0, 4.0, 5.0] {
+    ///     println!("{:?}", stream.update(value));
+    /// }
+    /// ```
+    pub fn new(look_back: i32, relative_weight: f32, start_at_bar: i32) -> Self {
+        KernelStream::with_kernel(RationalQuadraticKernel { look_back: look_back as f64, relative_weight: relative_weight as f64 }, start_at_bar)
+    }
+}
+
+impl KernelStream<GaussianKernel> {
+    /// Creates an empty stream with the parameters of [`crate::gaussian`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tech_analysis::GaussianStream;
+    /// let mut stream = GaussianStream::new(2, 3);
+    /// for value in [1.0, 2.0, 3.0, 4.0, 5.0] {
+    ///     println!("{:?}", stream.update(value));
+    /// }
+    /// ```
+    pub fn new(look_back: i32, start_at_bar: i32) -> Self {
+        KernelStream::with_kernel(GaussianKernel { look_back: look_back as f64 }, start_at_bar)
+    }
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentzian_classification::helper::example;
+    use polars::prelude::*;
+    use crate::lorentzian_classification::kernel::{gaussian, rational_quadratic};
+
+    #[test]
+    fn test_same_as_batch() -> Result<(), Box<dyn std::error::Error>> {
+        let df = example()?;
+        let close = df.column("close")?;
+        let values: Vec<f64> = close.f64()?.into_no_null_iter().collect();
+
+        let batch: Vec<Option<f64>> = rational_quadratic(close, 8, 8.0, 25)?.f64()?.into_iter().collect();
+        let mut stream = RationalQuadraticStream::new(8, 8.0, 25);
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(stream.update(*value), batch[i]);
+        }
+
+        let batch: Vec<Option<f64>> = gaussian(close, 6, 25)?.f64()?.into_iter().collect();
+        let mut stream = GaussianStream::new(6, 25);
+        let streamed: Vec<Option<f64>> = values.iter().map(|v| stream.update(*v)).collect();
+        assert!(streamed[..26].iter().all(|e| e.is_none()));
+        assert_eq!(streamed, batch);
+
+        // missing values are skipped as in the batch functions
+        let mut gaps = values[..200].to_vec();
+        gaps[50] = f64::NAN;
+        gaps[51] = f64::NAN;
+        let batch: Vec<Option<f64>> = gaussian(&Series::new("close", gaps.clone()), 6, 25)?.f64()?.into_iter().collect();
+        let mut stream = GaussianStream::new(6, 25);
+        let streamed: Vec<Option<f64>> = gaps.iter().map(|v| stream.update(*v)).collect();
+        assert_eq!(streamed, batch);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut stream = GaussianStream::new(2, 1);
+        assert_eq!(stream.update(1.0), None);
+        assert_eq!(stream.update(1.0), None);
+        assert_eq!(stream.update(1.0), Some(1.0));
+        stream.reset();
+        assert_eq!(stream.update(1.0), None);
+        assert_eq!(stream.kernel().look_back, 2.0);
+    }
+}
@@ -0,0 +1,319 @@
+/*
+ * @Author: uyplayer
+ * @Date: 2023/10/16 10:12
+ * @Email: uyplayer@qq.com
+ * @File: trade
+ * @Software: RustRover
+ * @Dir: tech_analysis / src/lorentzian_classification
+ * @Project_Name: tech_analysis
+ * @Description:
+ */
+
+
+//! trade lifecycle of lorentzian classification, entries and fixed or dynamic exits
+
+use crate::lorentzian_classification::types::Direction;
+
+
+/// How an open position is closed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExitMode {
+    /// Close after `holding_period` bars, an earlier signal flip resets the trade without an exit.
+    Fixed { holding_period: usize },
+    /// Close on the first kernel transition against the position, as plotted by pine.
+    Dynamic,
+}
+
+impl Default for ExitMode {
+    /// The fixed 4 bars exit of the tradingview script.
+    fn default() -> Self {
+        ExitMode::Fixed { holding_period: 4 }
+    }
+}
+
+/// Why a position was closed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExitReason {
+    /// The position was held for the fixed holding period, plotted as an exit in tradingview.
+    HoldingPeriod,
+    /// The kernel turned against the position, plotted as an exit in tradingview.
+    KernelChange,
+    /// The signal flipped before the holding period ended, tradingview plots no exit.
+    SignalFlip,
+    /// An entry in the opposite direction closed the position, tradingview plots no exit.
+    Reversal,
+}
+
+impl ExitReason {
+    /// Whether tradingview plots an exit for this reason.
+    pub fn is_plotted(&self) -> bool {
+        matches!(self, ExitReason::HoldingPeriod | ExitReason::KernelChange)
+    }
+}
+
+/// The kind of a trade event.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TradeEventKind {
+    /// A position was opened.
+    Entry,
+    /// A position was closed.
+    Exit(ExitReason),
+}
+
+/// An entry or exit of a position.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TradeEvent {
+    /// The bar of the event.
+    pub bar_index: usize,
+    /// The direction of the position, `LONG` or `SHORT`.
+    pub direction: Direction,
+    /// Whether the position was opened or closed.
+    pub kind: TradeEventKind,
+}
+
+/// The inputs of the state machine at a bar.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TradeBar {
+    /// The classifier signal.
+    pub signal: Direction,
+    /// Whether a long trade starts at the bar.
+    pub start_long: bool,
+    /// Whether a short trade starts at the bar.
+    pub start_short: bool,
+    /// Whether the kernel turned bullish at the bar, closes shorts in dynamic mode.
+    pub bullish_change: bool,
+    /// Whether the kernel turned bearish at the bar, closes longs in dynamic mode.
+    pub bearish_change: bool,
+}
+
+/// Position state machine over `Direction`.
+///
+/// The position is `NEUTRAL` (flat), `LONG` or `SHORT`. Every bar is processed in the same order as pine:
+/// the open position is closed first (holding period or signal flip in fixed mode, kernel change in
+/// dynamic mode), then a new trade is opened, closing the open position in the opposite direction.
+///
+/// The dynamic exits are the ones pine plots: the kernel exit of a long is the first bearish change after
+/// the last long entry, valid only when a bearish change also happened before that entry, and the same for
+/// shorts. They only depend on the entries and the kernel changes, so with the kernel filter disabled the
+/// kernel exit of a trade already closed by a reversal is still reported, as pine plots it, and leaves the
+/// open position unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionStateMachine {
+    exit_mode: ExitMode,
+    position: Direction,
+    entry_index: usize,
+    last_signal: Option<Direction>,
+    last_long_entry: Option<usize>,
+    last_short_entry: Option<usize>,
+    last_bullish_change: Option<usize>,
+    last_bearish_change: Option<usize>,
+}
+
+impl PositionStateMachine {
+    /// Creates a flat state machine.
+    pub fn new(exit_mode: ExitMode) -> Self {
+        if let ExitMode::Fixed { holding_period } = exit_mode {
+            if holding_period == 0 {
+                panic!("holding_period must be bigger than zero");
+            }
+        }
+        PositionStateMachine {
+            exit_mode,
+            position: Direction::NEUTRAL,
+            entry_index: 0,
+            last_signal: None,
+            last_long_entry: None,
+            last_short_entry: None,
+            last_bullish_change: None,
+            last_bearish_change: None,
+        }
+    }
+
+    /// The open position, `NEUTRAL` when flat.
+    pub fn position(&self) -> Direction {
+        self.position
+    }
+
+    /// The bar of the entry of the open position.
+    pub fn entry_index(&self) -> Option<usize> {
+        if self.position == Direction::NEUTRAL { None } else { Some(self.entry_index) }
+    }
+
+    fn close(&mut self, bar_index: usize, reason: ExitReason, events: &mut Vec<TradeEvent>) {
+        events.push(TradeEvent { bar_index, direction: self.position, kind: TradeEventKind::Exit(reason) });
+        self.position = Direction::NEUTRAL;
+    }
+
+    fn open(&mut self, bar_index: usize, direction: Direction, events: &mut Vec<TradeEvent>) {
+        if self.position != Direction::NEUTRAL {
+            self.close(bar_index, ExitReason::Reversal, events);
+        }
+        self.position = direction;
+        self.entry_index = bar_index;
+        events.push(TradeEvent { bar_index, direction, kind: TradeEventKind::Entry });
+    }
+
+    /// The kernel exit of a direction at a bar, closes the position when it is in that direction.
+    fn kernel_exit(&mut self, bar_index: usize, direction: Direction, events: &mut Vec<TradeEvent>) {
+        events.push(TradeEvent { bar_index, direction, kind: TradeEventKind::Exit(ExitReason::KernelChange) });
+        if self.position == direction {
+            self.position = Direction::NEUTRAL;
+        }
+    }
+
+    /// Processes a bar and returns its events in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `bar_index` - The index of the bar, bars must be processed in order.
+    /// * `bar` - The inputs at the bar.
+    pub fn update(&mut self, bar_index: usize, bar: &TradeBar) -> Vec<TradeEvent> {
+        let mut events = Vec::new();
+        let signal_changed = matches!(self.last_signal, Some(last) if last != bar.signal);
+
+        match self.exit_mode {
+            ExitMode::Fixed { holding_period } if self.position != Direction::NEUTRAL => {
+                if signal_changed {
+                    self.close(bar_index, ExitReason::SignalFlip, &mut events);
+                } else if bar_index - self.entry_index == holding_period {
+                    self.close(bar_index, ExitReason::HoldingPeriod, &mut events);
+                }
+            }
+            ExitMode::Dynamic => {
+                // pine isValidLongExit[1], the last bearish change is older than the last long entry
+                let is_valid = |change: Option<usize>, entry: Option<usize>| matches!((change, entry), (Some(change), Some(entry)) if change < entry);
+                let is_valid_long_exit = is_valid(self.last_bearish_change, self.last_long_entry);
+                let is_valid_short_exit = is_valid(self.last_bullish_change, self.last_short_entry);
+                if bar.bearish_change && is_valid_long_exit {
+                    self.kernel_exit(bar_index, Direction::LONG, &mut events);
+                }
+                if bar.bullish_change && is_valid_short_exit {
+                    self.kernel_exit(bar_index, Direction::SHORT, &mut events);
+                }
+            }
+            _ => {}
+        }
+
+        if bar.start_long {
+            self.open(bar_index, Direction::LONG, &mut events);
+            self.last_long_entry = Some(bar_index);
+        } else if bar.start_short {
+            self.open(bar_index, Direction::SHORT, &mut events);
+            self.last_short_entry = Some(bar_index);
+        }
+
+        self.last_signal = Some(bar.signal);
+        if bar.bullish_change {
+            self.last_bullish_change = Some(bar_index);
+        }
+        if bar.bearish_change {
+            self.last_bearish_change = Some(bar_index);
+        }
+        events
+    }
+}
+
+/// Runs the state machine over every bar.
+///
+/// # Arguments
+///
+/// * `bars` - The inputs of every bar.
+/// * `exit_mode` - How positions are closed.
+///
+/// # Returns
+///
+/// The entry and exit events in bar order.
+pub fn trade_events(bars: &[TradeBar], exit_mode: ExitMode) -> Vec<TradeEvent> {
+    let mut machine = PositionStateMachine::new(exit_mode);
+    bars.iter()
+        .enumerate()
+        .flat_map(|(i, bar)| machine.update(i, bar))
+        .collect()
+}
+
+
+// unit test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(signal: Direction) -> TradeBar {
+        TradeBar { signal, start_long: false, start_short: false, bullish_change: false, bearish_change: false }
+    }
+
+    #[test]
+    fn test_fixed_exit() {
+        let mut bars = vec![bar(Direction::NEUTRAL), bar(Direction::LONG), bar(Direction::LONG), bar(Direction::LONG), bar(Direction::LONG), bar(Direction::LONG), bar(Direction::LONG)];
+        bars[1].start_long = true;
+        let events = trade_events(&bars, ExitMode::default());
+        assert_eq!(events, vec![
+            TradeEvent { bar_index: 1, direction: Direction::LONG, kind: TradeEventKind::Entry },
+            TradeEvent { bar_index: 5, direction: Direction::LONG, kind: TradeEventKind::Exit(ExitReason::HoldingPeriod) },
+        ]);
+    }
+
+    #[test]
+    fn test_early_signal_flip() {
+        let mut bars = vec![bar(Direction::NEUTRAL), bar(Direction::LONG), bar(Direction::LONG), bar(Direction::SHORT), bar(Direction::SHORT), bar(Direction::SHORT)];
+        bars[1].start_long = true;
+        bars[3].start_short = true;
+        let events = trade_events(&bars, ExitMode::default());
+        assert_eq!(events, vec![
+            TradeEvent { bar_index: 1, direction: Direction::LONG, kind: TradeEventKind::Entry },
+            TradeEvent { bar_index: 3, direction: Direction::LONG, kind: TradeEventKind::Exit(ExitReason::SignalFlip) },
+            TradeEvent { bar_index: 3, direction: Direction::SHORT, kind: TradeEventKind::Entry },
+        ]);
+        assert!(!events[1].kind.eq(&TradeEventKind::Exit(ExitReason::HoldingPeriod)));
+    }
+
+    #[test]
+    fn test_dynamic_exit() {
+        let mut bars: Vec<TradeBar> = (0..8).map(|_| bar(Direction::LONG)).collect();
+        bars[0].bearish_change = true;
+        bars[2].start_long = true;
+        bars[6].bearish_change = true;
+        let mut machine = PositionStateMachine::new(ExitMode::Dynamic);
+        let events: Vec<TradeEvent> = bars.iter().enumerate().flat_map(|(i, b)| {
+            let events = machine.update(i, b);
+            if (2..6).contains(&i) {
+                assert_eq!(machine.position(), Direction::LONG);
+                assert_eq!(machine.entry_index(), Some(2));
+            }
+            events
+        }).collect();
+        assert_eq!(events[1], TradeEvent { bar_index: 6, direction: Direction::LONG, kind: TradeEventKind::Exit(ExitReason::KernelChange) });
+        assert_eq!(machine.position(), Direction::NEUTRAL);
+    }
+
+    #[test]
+    fn test_dynamic_exit_without_prior_transition() {
+        let mut bars: Vec<TradeBar> = (0..8).map(|_| bar(Direction::LONG)).collect();
+        bars[1].start_long = true;
+        bars[4].bearish_change = true;
+        bars[6].bearish_change = true;
+        let events = trade_events(&bars, ExitMode::Dynamic);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn test_dynamic_exit_after_reversal() {
+        // with the kernel filter off a short can open while the kernel is still bullish,
+        // pine still plots the kernel exit of the reversed long
+        let mut bars: Vec<TradeBar> = (0..8).map(|_| bar(Direction::LONG)).collect();
+        bars[0].bearish_change = true;
+        bars[2].start_long = true;
+        bars[4].start_short = true;
+        bars[6].bearish_change = true;
+        let events = trade_events(&bars, ExitMode::Dynamic);
+        assert_eq!(events, vec![
+            TradeEvent { bar_index: 2, direction: Direction::LONG, kind: TradeEventKind::Entry },
+            TradeEvent { bar_index: 4, direction: Direction::LONG, kind: TradeEventKind::Exit(ExitReason::Reversal) },
+            TradeEvent { bar_index: 4, direction: Direction::SHORT, kind: TradeEventKind::Entry },
+            TradeEvent { bar_index: 6, direction: Direction::LONG, kind: TradeEventKind::Exit(ExitReason::KernelChange) },
+        ]);
+        // the short is still open
+        let mut machine = PositionStateMachine::new(ExitMode::Dynamic);
+        bars.iter().enumerate().for_each(|(i, b)| { machine.update(i, b); });
+        assert_eq!(machine.position(), Direction::SHORT);
+    }
+}
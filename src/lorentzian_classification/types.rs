@@ -12,6 +12,7 @@
 
 //! all type declared here using in lorentzian classification
 
+use crate::lorentzian_classification::adaptive::AdaptiveBandwidth;
 
 
 
@@ -44,6 +45,10 @@ pub struct Settings<'a>{
     pub neighbors_count:i8,
     /// The maximum number of bars to look back.
     pub max_bars_back:i32,
+    /// The number of features used by the classifier, the first `feature_count` of `features`.
+    pub feature_count:i8,
+    /// The feature space, same as the f1 - f5 inputs of the tradingview script.
+    pub features:[FeatureSpec; 5],
     /// Flag to indicate whether exits should be shown.
     pub show_exits:bool,
     /// Flag to indicate whether dynamic exits should be used.
@@ -72,6 +77,12 @@ impl ConfigCheck for Settings<'_>{
         if self.max_bars_back <= 0 {
             panic!(" max_bars_back must be bigger than zero ");
         }
+        if !(2..=5).contains(&self.feature_count) {
+            panic!(" feature_count must be between 2 and 5 ");
+        }
+        for feature in &self.features[..self.feature_count as usize] {
+            feature.configuration_check();
+        }
         if self.ema_period <= 1 {
             panic!(" ema_period must be bigger than one ");
         }
@@ -82,8 +93,45 @@ impl ConfigCheck for Settings<'_>{
 
 }
 
+// feature setting
+/// A feature of the classifier with its parameters, same as the features of the pine `MLExtensions` library.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FeatureSpec {
+    /// Normalized RSI of close, `param_a` is the RSI length and `param_b` the smoothing length.
+    RSI { param_a: i32, param_b: i32 },
+    /// Normalized WaveTrend of hlc3, `param_a` is the channel length and `param_b` the average length.
+    WT { param_a: i32, param_b: i32 },
+    /// Normalized CCI of close, `param_a` is the CCI length and `param_b` the smoothing length.
+    CCI { param_a: i32, param_b: i32 },
+    /// Normalized ADX, `param_a` is the ADX length.
+    ADX { param_a: i32 },
+}
+
+/// The default feature space of the tradingview script.
+pub const DEFAULT_FEATURES: [FeatureSpec; 5] = [
+    FeatureSpec::RSI { param_a: 14, param_b: 1 },
+    FeatureSpec::WT { param_a: 10, param_b: 11 },
+    FeatureSpec::CCI { param_a: 20, param_b: 1 },
+    FeatureSpec::ADX { param_a: 20 },
+    FeatureSpec::RSI { param_a: 9, param_b: 1 },
+];
+
+impl ConfigCheck for FeatureSpec {
+    type Output = ();
+    fn configuration_check(&self) -> Self::Output {
+        let valid = match *self {
+            FeatureSpec::RSI { param_a, param_b } | FeatureSpec::WT { param_a, param_b } | FeatureSpec::CCI { param_a, param_b } => param_a > 0 && param_b > 0,
+            FeatureSpec::ADX { param_a } => param_a > 0,
+        };
+        if !valid {
+            panic!("feature parameters must be bigger than zero");
+        }
+    }
+}
+
 // filter setting
 /// a set of filters struct  used for classification.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Filters{
     /// Indicates whether the volatility filter is being used.
     pub use_volatility_filter: bool,
@@ -113,7 +161,10 @@ impl ConfigCheck for Filters {
 
 // kernel filter
 /// Represents a kernel filter used for data smoothing and estimation.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct KernelFilter {
+    /// Indicates whether entries must agree with the kernel trend.
+    pub use_kernel_filter: bool,
     /// Indicates whether the kernel estimate should be shown.
     pub show_kernel_estimate: bool,
     /// Indicates whether kernel smoothing should be used.
@@ -126,6 +177,9 @@ pub struct KernelFilter {
     pub regression_level: f32,
     /// The lag for crossover operations in the filter.
     pub crossover_lag: i32,
+    /// Adapts the bandwidth of the kernel lines to the volatility, `look_back_window` is then the bandwidth
+    /// of a bar with the fast and slow volatilities the same.
+    pub adaptive_bandwidth: Option<AdaptiveBandwidth>,
 }
 
 impl  ConfigCheck for KernelFilter{
@@ -143,7 +197,10 @@ impl  ConfigCheck for KernelFilter{
             panic!("regression_level must be greater tha 0");
         }
         if self.crossover_lag < 0 {
-            panic!("regression_level must be greater tha 0");
+            panic!("crossover_lag must not be negative");
+        }
+        if let Some(bandwidth) = &self.adaptive_bandwidth {
+            bandwidth.configuration_check();
         }
 
     }
@@ -151,6 +208,8 @@ impl  ConfigCheck for KernelFilter{
 }
 
 // market trend direction
+/// Market trend direction, the discriminant is the value pine uses for the labels and signals.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Direction{
     LONG = 1,
     SHORT = -1,
@@ -168,6 +227,8 @@ mod tests {
             source: "close",
             neighbors_count: 5,
             max_bars_back: 18,
+            feature_count: 5,
+            features: DEFAULT_FEATURES,
             show_exits: true,
             use_dynamic_exits: false,
             use_ema_filter: false,
@@ -178,6 +239,18 @@ mod tests {
         settings.configuration_check();
     }
     #[test]
+    fn test_feature_spec() {
+        FeatureSpec::ADX { param_a: 20 }.configuration_check();
+        for feature in DEFAULT_FEATURES.iter() {
+            feature.configuration_check();
+        }
+    }
+    #[test]
+    #[should_panic(expected = "feature parameters must be bigger than zero")]
+    fn test_invalid_feature_spec() {
+        FeatureSpec::RSI { param_a: 0, param_b: 1 }.configuration_check();
+    }
+    #[test]
     fn test_filters() {
         let filters = Filters{
             use_volatility_filter: false,
@@ -191,12 +264,14 @@ mod tests {
     #[test]
     fn test_kernel_filter(){
         let kernel = KernelFilter{
+            use_kernel_filter: true,
             show_kernel_estimate: false,
             use_kernel_smoothing: false,
             look_back_window: 0,
             relative_weight: 0.0,
             regression_level: 0.0,
             crossover_lag: 0,
+            adaptive_bandwidth: None,
         };
         kernel.configuration_check();
     }
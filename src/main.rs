@@ -14,9 +14,6 @@
 // unsafe code not allowed
 #![forbid(unsafe_code)]
 
-use tech_analysis::{rational_quadratic,rational_quadratic_tv};
-
-
 fn main() {
 
     println!("Hello, world!");